@@ -0,0 +1,61 @@
+//! Times lexing and parsing of large generated worksheets, and checks that the time grows
+//! linearly with the size of the input. Run with `cargo bench`.
+
+use std::time::{Duration, Instant};
+
+use ami::Engine;
+
+const RUNS: usize = 10;
+/// How much longer the larger input may take per byte before it counts as a regression. A
+/// linear lexer and parser stay near 1, while quadratic ones grow with the size difference.
+const MAX_SLOWDOWN: f64 = 3.0;
+
+/// A worksheet of about `lines` lines, using most of the syntax
+fn worksheet(lines: usize) -> String {
+    let mut source = String::new();
+    for i in 0..lines / 4 {
+        source += &format!("f_{i}(x) = 3x² + sin(x)/2 - √(x + {i}) + |x - 1|\n");
+        source += &format!("v_{i} = [1, 2, 3] ∙ ⟨{i}, 2.5, 1⟩ + ⌊f_{i}(2)⌋\n");
+        source += &format!("s_{i} = Σ(k = 1, 10, 1/k²) + ∫(0, π, sin(x), x) + 2ⁱ⁺¹\n");
+        source += &format!("g_{i} = (x, y) ↦ if x ≤ y then α else β × f_{i}(y)\n");
+    }
+    source
+}
+
+/// The median time of a few runs of `f`
+fn time(mut f: impl FnMut()) -> Duration {
+    let mut times: Vec<Duration> = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .collect();
+    times.sort();
+    times[RUNS / 2]
+}
+
+fn main() {
+    let mut per_byte: Vec<f64> = vec![];
+
+    for lines in [1_000, 8_000] {
+        let source = worksheet(lines);
+        let parse = time(|| {
+            Engine::check(&source).unwrap();
+        });
+
+        println!(
+            "{:>4} KB: lex and parse {:>10.3?}",
+            source.len() / 1024,
+            parse
+        );
+        per_byte.push(parse.as_secs_f64() / source.len() as f64);
+    }
+
+    let slowdown = per_byte[1] / per_byte[0];
+    assert!(
+        slowdown < MAX_SLOWDOWN,
+        "parsing takes {:.1} times as long per byte on the larger input",
+        slowdown
+    );
+}
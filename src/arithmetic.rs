@@ -0,0 +1,457 @@
+use std::cmp::Ordering;
+
+use crate::{math, BigInt, BinaryOp, Complex, Matrix, Rational, UnaryOp, Value};
+
+/// Exponents past this many digits in the result fall back to floats instead of hanging
+const MAX_EXACT_DIGITS: f64 = 1_000_000.0;
+
+fn expected_number(value: &Value) -> String {
+    format!("expected a real number, found {}", value)
+}
+
+fn to_f64(value: &Value) -> Result<f64, String> {
+    value.as_f64().ok_or_else(|| expected_number(value))
+}
+
+/// Integers and fractions can be combined without losing precision
+fn to_exact(value: &Value) -> Option<Rational> {
+    match value {
+        Value::Integer(n) => Some(n.clone().into()),
+        Value::Rational(r) => Some(r.clone()),
+        _ => None,
+    }
+}
+
+/// Collapses fractions with a denominator of 1 back into integers
+pub fn from_rational(r: Rational) -> Value {
+    if r.is_integer() {
+        Value::Integer(r.numer().clone())
+    } else {
+        Value::Rational(r)
+    }
+}
+
+fn to_complex(value: &Value) -> Result<Complex, String> {
+    match value {
+        Value::Complex(z) => Ok(*z),
+        value => value
+            .as_f64()
+            .map(Complex::from)
+            .ok_or_else(|| format!("expected a number, found {}", value)),
+    }
+}
+
+/// Collapses complex numbers with no imaginary part back into real numbers
+pub fn from_complex(z: Complex) -> Value {
+    if z.im == 0.0 {
+        Value::Number(z.re)
+    } else {
+        Value::Complex(z)
+    }
+}
+
+/// Whether raising a number with this many digits to `exponent` stays small enough to compute exactly
+fn exact_pow_fits(digits: f64, exponent: f64) -> bool {
+    digits.max(1.0) * exponent.abs() <= MAX_EXACT_DIGITS
+}
+
+/// Converts a vector of real numbers into a column matrix
+pub fn to_column(elements: &[Value]) -> Result<Matrix, String> {
+    let data = elements
+        .iter()
+        .map(|element| element.as_f64().ok_or_else(|| expected_number(element)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Matrix::new(data.len(), 1, data).unwrap())
+}
+
+pub fn from_column(matrix: &Matrix) -> Value {
+    Value::Vector(matrix.col(0).into_iter().map(Value::Number).collect())
+}
+
+pub fn unary(op: UnaryOp, value: Value) -> Result<Value, String> {
+    match (op, value) {
+        // |A| is the determinant, like in math
+        (UnaryOp::Abs, Value::Matrix(m)) => Ok(Value::Number(m.det()?)),
+        (UnaryOp::Pos, Value::Matrix(m)) => Ok(Value::Matrix(m)),
+        (UnaryOp::Neg, Value::Matrix(m)) => Ok(Value::Matrix(m.map(|x| -x))),
+        (UnaryOp::Floor, Value::Matrix(m)) => Ok(Value::Matrix(m.map(f64::floor))),
+        (UnaryOp::Ceil, Value::Matrix(m)) => Ok(Value::Matrix(m.map(f64::ceil))),
+        (UnaryOp::Round, Value::Matrix(m)) => Ok(Value::Matrix(m.map(f64::round))),
+        (UnaryOp::Abs, Value::Vector(elements)) => {
+            let mut sum = 0.0;
+            for element in elements {
+                sum += to_f64(&unary(UnaryOp::Abs, element)?)?.powi(2);
+            }
+            Ok(Value::Number(sum.sqrt()))
+        }
+        (
+            op @ (UnaryOp::Pos | UnaryOp::Neg | UnaryOp::Floor | UnaryOp::Ceil | UnaryOp::Round),
+            Value::Vector(elements),
+        ) => Ok(Value::Vector(
+            elements
+                .into_iter()
+                .map(|element| unary(op, element))
+                .collect::<Result<_, _>>()?,
+        )),
+        (UnaryOp::Not, Value::Bool(b)) => Ok(Value::Bool(!b)),
+        (UnaryOp::Not, value) => Err(format!("expected a boolean, found {}", value)),
+        (UnaryOp::Pos, value @ (Value::Integer(_) | Value::Rational(_) | Value::Number(_))) => {
+            Ok(value)
+        }
+        (UnaryOp::Neg, Value::Rational(r)) => Ok(Value::Rational(r.neg())),
+        (UnaryOp::Abs, Value::Rational(r)) => Ok(Value::Rational(r.abs())),
+        (UnaryOp::Floor, Value::Rational(r)) => Ok(Value::Integer(r.floor())),
+        (UnaryOp::Ceil, Value::Rational(r)) => Ok(Value::Integer(r.ceil())),
+        (UnaryOp::Round, Value::Rational(r)) => Ok(Value::Integer(r.round())),
+        (UnaryOp::Neg, Value::Integer(n)) => Ok(Value::Integer(-&n)),
+        (UnaryOp::Abs, Value::Integer(n)) => Ok(Value::Integer(n.abs())),
+        (UnaryOp::Floor | UnaryOp::Ceil | UnaryOp::Round, Value::Integer(n)) => {
+            Ok(Value::Integer(n))
+        }
+        (UnaryOp::Fact, Value::Integer(n)) => {
+            if n.is_negative() {
+                return Err(format!("{}! is undefined for negative integers", n));
+            }
+            Ok(Value::Integer(n.factorial(1)))
+        }
+        (UnaryOp::DoubleFact, Value::Integer(n)) => {
+            if n < BigInt::from(-1) {
+                return Err(format!(
+                    "{}!! is only defined for integers greater than or equal to -1",
+                    n
+                ));
+            }
+            Ok(Value::Integer(n.factorial(2)))
+        }
+        (op, Value::Complex(z)) => Ok(match op {
+            UnaryOp::Pos => Value::Complex(z),
+            UnaryOp::Neg => Value::Complex(-z),
+            UnaryOp::Abs => Value::Number(z.abs()),
+            UnaryOp::Floor => from_complex(Complex::new(z.re.floor(), z.im.floor())),
+            UnaryOp::Ceil => from_complex(Complex::new(z.re.ceil(), z.im.ceil())),
+            UnaryOp::Round => from_complex(Complex::new(z.re.round(), z.im.round())),
+            UnaryOp::Sqrt => from_complex(z.sqrt()),
+            UnaryOp::Cbrt => from_complex(z.cbrt()),
+            UnaryOp::Fort => from_complex(z.sqrt().sqrt()),
+            UnaryOp::Degree => from_complex(z.scale(std::f64::consts::PI / 180.0)),
+            UnaryOp::Fact | UnaryOp::DoubleFact => {
+                return Err(format!(
+                    "factorials of complex numbers like {} are not supported",
+                    z
+                ))
+            }
+            UnaryOp::Not => unreachable!(),
+        }),
+        (op @ (UnaryOp::Sqrt | UnaryOp::Fort), value)
+            if value.as_f64().is_some_and(|x| x < 0.0) =>
+        {
+            unary(op, Value::Complex(to_complex(&value)?))
+        }
+        (op, value) => {
+            let x = to_f64(&value)?;
+            Ok(Value::Number(match op {
+                UnaryOp::Pos => x,
+                UnaryOp::Neg => -x,
+                UnaryOp::Abs => x.abs(),
+                UnaryOp::Floor => x.floor(),
+                UnaryOp::Ceil => x.ceil(),
+                UnaryOp::Round => x.round(),
+                UnaryOp::Sqrt => x.sqrt(),
+                UnaryOp::Cbrt => x.cbrt(),
+                UnaryOp::Fort => x.powf(0.25),
+                UnaryOp::Degree => x.to_radians(),
+                UnaryOp::Fact => math::factorial(x)?,
+                UnaryOp::DoubleFact => math::double_factorial(x)?,
+                UnaryOp::Not => unreachable!(),
+            }))
+        }
+    }
+}
+
+pub fn binary(op: BinaryOp, left: Value, right: Value) -> Result<Value, String> {
+    match op {
+        BinaryOp::And | BinaryOp::Or => match (left, right) {
+            (Value::Bool(a), Value::Bool(b)) => Ok(Value::Bool(match op {
+                BinaryOp::And => a && b,
+                _ => a || b,
+            })),
+            (Value::Bool(_), value) | (value, _) => {
+                Err(format!("expected a boolean, found {}", value))
+            }
+        },
+        BinaryOp::Eq | BinaryOp::Ne => {
+            let equal = match (&left, &right) {
+                (Value::Bool(a), Value::Bool(b)) => a == b,
+                (Value::Matrix(a), Value::Matrix(b)) => a == b,
+                (Value::Vector(a), Value::Vector(b)) => {
+                    let mut equal = a.len() == b.len();
+                    for (a, b) in a.iter().zip(b) {
+                        equal &= matches!(
+                            binary(BinaryOp::Eq, a.clone(), b.clone())?,
+                            Value::Bool(true)
+                        );
+                    }
+                    equal
+                }
+                (Value::Complex(_), _) | (_, Value::Complex(_)) => {
+                    to_complex(&left)? == to_complex(&right)?
+                }
+                _ => compare(&left, &right)? == Some(Ordering::Equal),
+            };
+            Ok(Value::Bool(equal == (op == BinaryOp::Eq)))
+        }
+        BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+            let ordering = compare(&left, &right)?;
+            Ok(Value::Bool(match ordering {
+                Some(ordering) => match op {
+                    BinaryOp::Lt => ordering.is_lt(),
+                    BinaryOp::Le => ordering.is_le(),
+                    BinaryOp::Gt => ordering.is_gt(),
+                    _ => ordering.is_ge(),
+                },
+                None => false,
+            }))
+        }
+        _ if matches!(left, Value::Matrix(_)) || matches!(right, Value::Matrix(_)) => {
+            matrix_binary(op, left, right)
+        }
+        _ if matches!(left, Value::Vector(_)) || matches!(right, Value::Vector(_)) => {
+            vector_binary(op, left, right)
+        }
+        // Dot and cross products of scalars are just multiplication
+        BinaryOp::Dot | BinaryOp::Cross => binary(BinaryOp::Mul, left, right),
+        _ => match (left, right) {
+            (Value::Integer(a), Value::Integer(b)) => integer_binary(op, a, b),
+            (left @ Value::Complex(_), right) | (left, right @ Value::Complex(_)) => {
+                complex_binary(op, to_complex(&left)?, to_complex(&right)?)
+            }
+            (left, right) => match (to_exact(&left), to_exact(&right)) {
+                (Some(a), Some(b)) => Ok(rational_binary(op, a, b)),
+                _ => {
+                    let a = to_f64(&left)?;
+                    let b = to_f64(&right)?;
+                    Ok(float_binary(op, a, b))
+                }
+            },
+        },
+    }
+}
+
+/// Orders two numbers, returning `None` when either is NaN
+pub fn compare(left: &Value, right: &Value) -> Result<Option<Ordering>, String> {
+    match (left, right) {
+        (Value::Integer(a), Value::Integer(b)) => Ok(Some(a.cmp(b))),
+        _ => match (to_exact(left), to_exact(right)) {
+            (Some(a), Some(b)) => Ok(Some(a.cmp(&b))),
+            _ => match (left.as_f64(), right.as_f64()) {
+                (Some(a), Some(b)) => Ok(a.partial_cmp(&b)),
+                _ => Err(format!("cannot order {} and {}", left, right)),
+            },
+        },
+    }
+}
+
+fn matrix_binary(op: BinaryOp, left: Value, right: Value) -> Result<Value, String> {
+    match (op, left, right) {
+        (BinaryOp::Add, Value::Matrix(a), Value::Matrix(b)) => {
+            Ok(Value::Matrix(a.zip_with(&b, |a, b| a + b)?))
+        }
+        (BinaryOp::Sub, Value::Matrix(a), Value::Matrix(b)) => {
+            Ok(Value::Matrix(a.zip_with(&b, |a, b| a - b)?))
+        }
+        (BinaryOp::Mul | BinaryOp::Dot, Value::Matrix(a), Value::Matrix(b)) => {
+            Ok(Value::Matrix(a.mul(&b)?))
+        }
+        (BinaryOp::Mul | BinaryOp::Dot, Value::Matrix(a), Value::Vector(v)) => {
+            Ok(from_column(&a.mul(&to_column(&v)?)?))
+        }
+        (BinaryOp::Mul | BinaryOp::Dot, Value::Vector(v), Value::Matrix(a)) => Ok(from_column(
+            &to_column(&v)?.transpose().mul(&a)?.transpose(),
+        )),
+        (BinaryOp::Pow, Value::Matrix(a), Value::Integer(n)) => {
+            let exponent = n.to_f64();
+            if exponent.abs() > u32::MAX as f64 {
+                return Err(format!("the exponent {} is too large", n));
+            }
+            Ok(Value::Matrix(a.pow(exponent as i64)?))
+        }
+        (BinaryOp::Pow, Value::Matrix(_), exponent) => Err(format!(
+            "matrices can only be raised to integer powers, not {}",
+            exponent
+        )),
+        (op @ (BinaryOp::Mul | BinaryOp::Div), Value::Matrix(a), scalar)
+            if scalar.as_f64().is_some() =>
+        {
+            let scalar = scalar.as_f64().unwrap();
+            Ok(Value::Matrix(match op {
+                BinaryOp::Mul => a.map(|x| x * scalar),
+                _ => a.map(|x| x / scalar),
+            }))
+        }
+        (BinaryOp::Mul, scalar, Value::Matrix(a)) if scalar.as_f64().is_some() => {
+            let scalar = scalar.as_f64().unwrap();
+            Ok(Value::Matrix(a.map(|x| x * scalar)))
+        }
+        (op, left, right) => Err(format!("cannot use {} on {} and {}", op, left, right)),
+    }
+}
+
+fn vector_binary(op: BinaryOp, left: Value, right: Value) -> Result<Value, String> {
+    let elementwise = |op: BinaryOp, a: Vec<Value>, b: Vec<Value>| -> Result<Vec<Value>, String> {
+        a.into_iter()
+            .zip(b)
+            .map(|(a, b)| binary(op, a, b))
+            .collect()
+    };
+    let scale = |op: BinaryOp, v: Vec<Value>, scalar: &Value, scalar_first: bool| {
+        v.into_iter()
+            .map(|x| match scalar_first {
+                true => binary(op, scalar.clone(), x),
+                false => binary(op, x, scalar.clone()),
+            })
+            .collect::<Result<Vec<_>, _>>()
+    };
+
+    match (op, left, right) {
+        (BinaryOp::Add | BinaryOp::Sub | BinaryOp::Dot | BinaryOp::Cross, Value::Vector(a), Value::Vector(b))
+            if a.len() != b.len() =>
+        {
+            Err(format!(
+                "vectors have different lengths, {} and {}",
+                a.len(),
+                b.len()
+            ))
+        }
+        (BinaryOp::Add | BinaryOp::Sub, Value::Vector(a), Value::Vector(b)) => {
+            Ok(Value::Vector(elementwise(op, a, b)?))
+        }
+        (BinaryOp::Dot, Value::Vector(a), Value::Vector(b)) => {
+            let mut sum = Value::from(0);
+            for product in elementwise(BinaryOp::Mul, a, b)? {
+                sum = binary(BinaryOp::Add, sum, product)?;
+            }
+            Ok(sum)
+        }
+        (BinaryOp::Cross, Value::Vector(a), Value::Vector(b)) => {
+            let [a1, a2, a3] = <[Value; 3]>::try_from(a)
+                .map_err(|a| format!("cross products need 3-D vectors, not {}-D", a.len()))?;
+            let [b1, b2, b3] = <[Value; 3]>::try_from(b).unwrap();
+            let component = |a: &Value, b: &Value, c: &Value, d: &Value| {
+                binary(
+                    BinaryOp::Sub,
+                    binary(BinaryOp::Mul, a.clone(), b.clone())?,
+                    binary(BinaryOp::Mul, c.clone(), d.clone())?,
+                )
+            };
+            Ok(Value::Vector(vec![
+                component(&a2, &b3, &a3, &b2)?,
+                component(&a3, &b1, &a1, &b3)?,
+                component(&a1, &b2, &a2, &b1)?,
+            ]))
+        }
+        (BinaryOp::Mul, Value::Vector(_), Value::Vector(_)) => Err(
+            "vectors can't be multiplied directly, use ∙ for the dot product or × for the cross product"
+                .to_string(),
+        ),
+        (BinaryOp::Mul | BinaryOp::Dot | BinaryOp::Cross, Value::Vector(v), scalar) => {
+            Ok(Value::Vector(scale(BinaryOp::Mul, v, &scalar, false)?))
+        }
+        (BinaryOp::Mul | BinaryOp::Dot | BinaryOp::Cross, scalar, Value::Vector(v)) => {
+            Ok(Value::Vector(scale(BinaryOp::Mul, v, &scalar, true)?))
+        }
+        (BinaryOp::Div, Value::Vector(v), scalar) if !matches!(scalar, Value::Vector(_)) => {
+            Ok(Value::Vector(scale(BinaryOp::Div, v, &scalar, false)?))
+        }
+        (op, left, right) => Err(format!("cannot use {} on {} and {}", op, left, right)),
+    }
+}
+
+fn integer_binary(op: BinaryOp, a: BigInt, b: BigInt) -> Result<Value, String> {
+    Ok(match op {
+        BinaryOp::Add => Value::Integer(&a + &b),
+        BinaryOp::Sub => Value::Integer(&a - &b),
+        BinaryOp::Mul => Value::Integer(&a * &b),
+        BinaryOp::Div => match a.div_rem(&b) {
+            Some((quotient, remainder)) if remainder.is_zero() => Value::Integer(quotient),
+            Some(_) => Value::Rational(Rational::new(a, b).unwrap()),
+            None => Value::Number(a.to_f64() / b.to_f64()),
+        },
+        BinaryOp::Mod => match a.div_rem(&b) {
+            Some((_, remainder)) => Value::Integer(remainder),
+            None => Value::Number(f64::NAN),
+        },
+        BinaryOp::Pow => match b.to_u32() {
+            Some(exponent) if exact_pow_fits(a.abs().to_f64().log10(), exponent as f64) => {
+                Value::Integer(a.pow(exponent))
+            }
+            _ => rational_binary(op, a.into(), b.into()),
+        },
+        _ => unreachable!(),
+    })
+}
+
+fn rational_binary(op: BinaryOp, a: Rational, b: Rational) -> Value {
+    match op {
+        BinaryOp::Add => from_rational(a.add(&b)),
+        BinaryOp::Sub => from_rational(a.sub(&b)),
+        BinaryOp::Mul => from_rational(a.mul(&b)),
+        BinaryOp::Div => match a.div(&b) {
+            Some(quotient) => from_rational(quotient),
+            None => Value::Number(a.to_f64() / b.to_f64()),
+        },
+        BinaryOp::Mod => match a.rem(&b) {
+            Some(remainder) => from_rational(remainder),
+            None => Value::Number(f64::NAN),
+        },
+        BinaryOp::Pow => {
+            let exponent = b.to_f64();
+            let digits = a.numer().abs().to_f64().log10() + a.denom().to_f64().log10();
+            let power = if b.is_integer()
+                && exact_pow_fits(digits, exponent)
+                && exponent.abs() <= u32::MAX as f64
+            {
+                a.pow(exponent as i64)
+            } else {
+                None
+            };
+            match power {
+                Some(power) => from_rational(power),
+                None => float_binary(op, a.to_f64(), exponent),
+            }
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn float_binary(op: BinaryOp, a: f64, b: f64) -> Value {
+    Value::Number(match op {
+        BinaryOp::Add => a + b,
+        BinaryOp::Sub => a - b,
+        BinaryOp::Mul => a * b,
+        BinaryOp::Div => a / b,
+        BinaryOp::Mod => a % b,
+        // Fractional powers of negative numbers have complex principal values
+        BinaryOp::Pow if a < 0.0 && b.fract() != 0.0 => {
+            return from_complex(Complex::from(a).pow(Complex::from(b)))
+        }
+        BinaryOp::Pow => a.powf(b),
+        _ => unreachable!(),
+    })
+}
+
+fn complex_binary(op: BinaryOp, a: Complex, b: Complex) -> Result<Value, String> {
+    Ok(from_complex(match op {
+        BinaryOp::Add => a + b,
+        BinaryOp::Sub => a - b,
+        BinaryOp::Mul => a * b,
+        BinaryOp::Div => a / b,
+        BinaryOp::Pow => a.pow(b),
+        BinaryOp::Mod => {
+            return Err(format!(
+                "modulo is not defined for complex numbers like {}",
+                if a.im != 0.0 { a } else { b }
+            ))
+        }
+        _ => unreachable!(),
+    }))
+}
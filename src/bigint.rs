@@ -0,0 +1,640 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    ops::{Add, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+const BASE: u64 = 1_000_000_000;
+const BASE_DIGITS: usize = 9;
+const KARATSUBA_THRESHOLD: usize = 32;
+
+/// An arbitrary-precision integer, stored as base 10⁹ limbs from least to most significant
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    limbs: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    pub fn one() -> Self {
+        Self::from(1)
+    }
+
+    fn from_limbs(negative: bool, mut limbs: Vec<u32>) -> Self {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        Self {
+            negative: negative && !limbs.is_empty(),
+            limbs,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn is_one(&self) -> bool {
+        !self.negative && self.limbs == [1]
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn is_even(&self) -> bool {
+        self.limbs.first().is_none_or(|limb| limb % 2 == 0)
+    }
+
+    pub fn abs(&self) -> Self {
+        Self {
+            negative: false,
+            limbs: self.limbs.clone(),
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let mut value = 0.0;
+        for limb in self.limbs.iter().rev() {
+            value = value * BASE as f64 + *limb as f64;
+        }
+        if self.negative {
+            -value
+        } else {
+            value
+        }
+    }
+
+    pub fn to_u32(&self) -> Option<u32> {
+        if self.negative {
+            return None;
+        }
+        match self.limbs.as_slice() {
+            [] => Some(0),
+            [low] => Some(*low),
+            [low, high] => u32::try_from(*high as u64 * BASE + *low as u64).ok(),
+            _ => None,
+        }
+    }
+
+    /// Converts a float with no fractional part, returning `None` for infinities and NaN
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() || value.fract() != 0.0 {
+            return None;
+        }
+
+        let negative = value < 0.0;
+        let mut value = value.abs();
+        let mut limbs = vec![];
+        while value >= 1.0 {
+            limbs.push((value % BASE as f64) as u32);
+            value = (value / BASE as f64).floor();
+        }
+        Some(Self::from_limbs(negative, limbs))
+    }
+
+    fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+        a.len()
+            .cmp(&b.len())
+            .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+    }
+
+    fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+        let mut limbs = Vec::with_capacity(a.len().max(b.len()) + 1);
+        let mut carry = 0u64;
+        for i in 0..a.len().max(b.len()) {
+            let sum = carry
+                + a.get(i).copied().unwrap_or(0) as u64
+                + b.get(i).copied().unwrap_or(0) as u64;
+            limbs.push((sum % BASE) as u32);
+            carry = sum / BASE;
+        }
+        if carry > 0 {
+            limbs.push(carry as u32);
+        }
+        limbs
+    }
+
+    /// Subtracts `b` from `a`, where `|a| ≥ |b|`
+    fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+        let mut limbs = Vec::with_capacity(a.len());
+        let mut borrow = 0i64;
+        for (i, limb) in a.iter().enumerate() {
+            let mut diff = *limb as i64 - borrow - b.get(i).copied().unwrap_or(0) as i64;
+            borrow = 0;
+            if diff < 0 {
+                diff += BASE as i64;
+                borrow = 1;
+            }
+            limbs.push(diff as u32);
+        }
+        limbs
+    }
+
+    /// Long multiplication, which is faster than Karatsuba below this many limbs
+    fn mul_schoolbook(a: &[u32], b: &[u32]) -> Vec<u32> {
+        let mut limbs = vec![0u64; a.len() + b.len() + 1];
+        for (i, x) in a.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, y) in b.iter().enumerate() {
+                let product = limbs[i + j] + *x as u64 * *y as u64 + carry;
+                limbs[i + j] = product % BASE;
+                carry = product / BASE;
+            }
+            let mut k = i + b.len();
+            while carry > 0 {
+                let sum = limbs[k] + carry;
+                limbs[k] = sum % BASE;
+                carry = sum / BASE;
+                k += 1;
+            }
+        }
+        limbs.into_iter().map(|limb| limb as u32).collect()
+    }
+
+    /// Adds `b` into `a` starting at limb `offset`, where `a` is long enough for the sum
+    fn add_shifted(a: &mut [u32], b: &[u32], offset: usize) {
+        let mut carry = 0u64;
+        for (i, limb) in a[offset..].iter_mut().enumerate() {
+            if i >= b.len() && carry == 0 {
+                break;
+            }
+            let sum = *limb as u64 + b.get(i).copied().unwrap_or(0) as u64 + carry;
+            *limb = (sum % BASE) as u32;
+            carry = sum / BASE;
+        }
+    }
+
+    /// Karatsuba multiplication, which splits both numbers in half and gets by with three
+    /// half-size products instead of four
+    fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+        // Intermediate products have leading zero limbs, which shouldn't count towards the size
+        let trim =
+            |limbs: &[u32]| limbs.len() - limbs.iter().rev().take_while(|&&limb| limb == 0).count();
+        let (a, b) = (&a[..trim(a)], &b[..trim(b)]);
+        let (a, b) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+        if b.is_empty() {
+            return vec![];
+        }
+        if b.len() < KARATSUBA_THRESHOLD {
+            return Self::mul_schoolbook(a, b);
+        }
+
+        let mut limbs = vec![0u32; a.len() + b.len() + 1];
+        let half = a.len() / 2;
+        if b.len() <= half {
+            // Too lopsided to split evenly, so multiply b by one piece of a at a time
+            for (i, chunk) in a.chunks(b.len()).enumerate() {
+                Self::add_shifted(&mut limbs, &Self::mul_magnitude(chunk, b), i * b.len());
+            }
+            return limbs;
+        }
+
+        let (a_low, a_high) = a.split_at(half);
+        let (b_low, b_high) = b.split_at(half);
+        let low = Self::mul_magnitude(a_low, b_low);
+        let high = Self::mul_magnitude(a_high, b_high);
+        let sums = Self::mul_magnitude(
+            &Self::add_magnitude(a_low, a_high),
+            &Self::add_magnitude(b_low, b_high),
+        );
+        // (a_low + a_high)(b_low + b_high) - low - high = a_low b_high + a_high b_low
+        let middle = Self::sub_magnitude(&Self::sub_magnitude(&sums, &low), &high);
+        Self::add_shifted(&mut limbs, &low, 0);
+        Self::add_shifted(&mut limbs, &middle, half);
+        Self::add_shifted(&mut limbs, &high, 2 * half);
+        limbs
+    }
+
+    fn mul_small(a: &[u32], b: u32) -> Vec<u32> {
+        let mut limbs = Vec::with_capacity(a.len() + 1);
+        let mut carry = 0u64;
+        for limb in a {
+            let product = *limb as u64 * b as u64 + carry;
+            limbs.push((product % BASE) as u32);
+            carry = product / BASE;
+        }
+        if carry > 0 {
+            limbs.push(carry as u32);
+        }
+        limbs
+    }
+
+    /// Divides `a` by a single limb, returning the quotient limbs and the remainder
+    fn div_small(a: &[u32], b: u32) -> (Vec<u32>, u32) {
+        let mut quotient = vec![0u32; a.len()];
+        let mut remainder = 0u64;
+        for i in (0..a.len()).rev() {
+            let current = remainder * BASE + a[i] as u64;
+            quotient[i] = (current / b as u64) as u32;
+            remainder = current % b as u64;
+        }
+        (quotient, remainder as u32)
+    }
+
+    /// Long division of magnitudes with Knuth's Algorithm D, where `b` has at least two limbs
+    /// and `a` is at least as long as `b`
+    fn div_rem_magnitude(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+        let n = b.len();
+        let m = a.len() - n;
+        // Scale both so the divisor's top limb is at least BASE / 2, which makes the estimate
+        // of each quotient digit from the top limbs at most two too large
+        let scale = (BASE / (b[n - 1] as u64 + 1)) as u32;
+        let v = Self::mul_small(b, scale);
+        let mut u = Self::mul_small(a, scale);
+        u.resize(a.len() + 1, 0);
+        let (top, second) = (v[n - 1] as u64, v[n - 2] as u64);
+
+        let mut quotient = vec![0u32; m + 1];
+        for j in (0..=m).rev() {
+            let numerator = u[j + n] as u64 * BASE + u[j + n - 1] as u64;
+            let mut digit = numerator / top;
+            let mut rest = numerator % top;
+            while digit >= BASE || digit * second > rest * BASE + u[j + n - 2] as u64 {
+                digit -= 1;
+                rest += top;
+                if rest >= BASE {
+                    break;
+                }
+            }
+
+            // Subtract digit × v from the window of u it lines up with
+            let mut carry = 0u64;
+            let mut borrow = 0i64;
+            for i in 0..=n {
+                let product = digit * v.get(i).copied().unwrap_or(0) as u64 + carry;
+                carry = product / BASE;
+                let mut diff = u[j + i] as i64 - (product % BASE) as i64 - borrow;
+                borrow = 0;
+                if diff < 0 {
+                    diff += BASE as i64;
+                    borrow = 1;
+                }
+                u[j + i] = diff as u32;
+            }
+
+            // The estimate was one too large, so add v back
+            if borrow > 0 {
+                digit -= 1;
+                let mut carry = 0u64;
+                for i in 0..=n {
+                    let sum = u[j + i] as u64 + v.get(i).copied().unwrap_or(0) as u64 + carry;
+                    u[j + i] = (sum % BASE) as u32;
+                    carry = sum / BASE;
+                }
+            }
+            quotient[j] = digit as u32;
+        }
+
+        let (remainder, _) = Self::div_small(&u[..n], scale);
+        (quotient, remainder)
+    }
+
+    /// Truncated division, so the remainder has the same sign as `self` like `%` on primitives
+    pub fn div_rem(&self, divisor: &Self) -> Option<(Self, Self)> {
+        let (quotient, remainder) = match divisor.limbs.as_slice() {
+            [] => return None,
+            _ if Self::cmp_magnitude(&self.limbs, &divisor.limbs) == Ordering::Less => {
+                (vec![], self.limbs.clone())
+            }
+            &[limb] => {
+                let (quotient, remainder) = Self::div_small(&self.limbs, limb);
+                (quotient, vec![remainder])
+            }
+            limbs => Self::div_rem_magnitude(&self.limbs, limbs),
+        };
+
+        Some((
+            Self::from_limbs(self.negative != divisor.negative, quotient),
+            Self::from_limbs(self.negative, remainder),
+        ))
+    }
+
+    pub fn pow(&self, mut exponent: u32) -> Self {
+        let mut result = Self::one();
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = &result * &base;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = &base * &base;
+            }
+        }
+        result
+    }
+
+    pub fn gcd(&self, other: &Self) -> Self {
+        let mut a = self.abs();
+        let mut b = other.abs();
+        while !b.is_zero() {
+            let (_, r) = a.div_rem(&b).unwrap();
+            a = b;
+            b = r;
+        }
+        a
+    }
+
+    pub fn lcm(&self, other: &Self) -> Self {
+        if self.is_zero() || other.is_zero() {
+            return Self::zero();
+        }
+        let (quotient, _) = (self * other).abs().div_rem(&self.gcd(other)).unwrap();
+        quotient
+    }
+
+    /// The product of every integer from `self` down to 1 or 2, stepping by `step`
+    pub fn factorial(&self, step: u32) -> Self {
+        let Some(mut n) = self.to_u32().filter(|&n| (n as u64) < BASE) else {
+            let mut product = Self::one();
+            let mut n = self.clone();
+            let step = Self::from(step);
+            while n > Self::one() {
+                product = &product * &n;
+                n = &n - &step;
+            }
+            return product;
+        };
+
+        // Multiply the factors together while they fit in a limb, then multiply those in pairs
+        // so the big products are between numbers of similar size, where Karatsuba pays off
+        let mut products = vec![];
+        let mut factors = 1u64;
+        while n > 1 {
+            if factors * n as u64 >= BASE {
+                products.push(vec![factors as u32]);
+                factors = 1;
+            }
+            factors *= n as u64;
+            n = n.saturating_sub(step);
+        }
+        products.push(vec![factors as u32]);
+        while products.len() > 1 {
+            products = products
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => Self::mul_magnitude(a, b),
+                    _ => pair[0].clone(),
+                })
+                .collect();
+        }
+        Self::from_limbs(false, products.pop().unwrap())
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let negative = value < 0;
+        let mut value = value.unsigned_abs();
+        let mut limbs = vec![];
+        while value > 0 {
+            limbs.push((value % BASE) as u32);
+            value /= BASE;
+        }
+        Self::from_limbs(negative, limbs)
+    }
+}
+
+impl From<u32> for BigInt {
+    fn from(value: u32) -> Self {
+        Self::from(value as i64)
+    }
+}
+
+impl From<i32> for BigInt {
+    fn from(value: i32) -> Self {
+        Self::from(value as i64)
+    }
+}
+
+impl FromStr for BigInt {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(format!("'{}' is not an integer", s));
+        }
+
+        let mut limbs = vec![];
+        let mut end = digits.len();
+        while end > 0 {
+            let start = end.saturating_sub(BASE_DIGITS);
+            limbs.push(digits[start..end].parse().unwrap());
+            end = start;
+        }
+        Ok(Self::from_limbs(negative, limbs))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negative {
+            write!(f, "-")?;
+        }
+        match self.limbs.split_last() {
+            Some((last, rest)) => {
+                write!(f, "{}", last)?;
+                for limb in rest.iter().rev() {
+                    write!(f, "{:09}", limb)?;
+                }
+                Ok(())
+            }
+            None => write!(f, "0"),
+        }
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => Self::cmp_magnitude(&self.limbs, &other.limbs),
+            (true, true) => Self::cmp_magnitude(&other.limbs, &self.limbs),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_limbs(!self.negative, self.limbs.clone())
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::from_limbs(
+                self.negative,
+                BigInt::add_magnitude(&self.limbs, &other.limbs),
+            );
+        }
+
+        match BigInt::cmp_magnitude(&self.limbs, &other.limbs) {
+            Ordering::Less => BigInt::from_limbs(
+                other.negative,
+                BigInt::sub_magnitude(&other.limbs, &self.limbs),
+            ),
+            _ => BigInt::from_limbs(
+                self.negative,
+                BigInt::sub_magnitude(&self.limbs, &other.limbs),
+            ),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &-other
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        BigInt::from_limbs(
+            self.negative != other.negative,
+            BigInt::mul_magnitude(&self.limbs, &other.limbs),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(s: &str) -> BigInt {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_and_display_round_trip() {
+        for s in [
+            "0",
+            "7",
+            "-7",
+            "999999999",
+            "1000000000",
+            "-1000000000000000001",
+            "123456789012345678901234567890",
+        ] {
+            assert_eq!(int(s).to_string(), s);
+        }
+        assert_eq!(int("-0").to_string(), "0");
+        assert_eq!(int("000123").to_string(), "123");
+        assert!("12a".parse::<BigInt>().is_err());
+        assert!("-".parse::<BigInt>().is_err());
+    }
+
+    #[test]
+    fn division_truncates_towards_zero() {
+        let div_rem = |a: i64, b: i64| {
+            let (q, r) = BigInt::from(a).div_rem(&BigInt::from(b)).unwrap();
+            (q.to_string(), r.to_string())
+        };
+        for (a, b) in [(7, 2), (-7, 2), (7, -2), (-7, -2), (6, 3), (-6, 3), (1, 5)] {
+            assert_eq!(div_rem(a, b), ((a / b).to_string(), (a % b).to_string()));
+        }
+        assert!(BigInt::one().div_rem(&BigInt::zero()).is_none());
+    }
+
+    #[test]
+    fn long_division() {
+        let a = BigInt::from(2).pow(5000);
+        let b = BigInt::from(3).pow(3000);
+        for (a, b) in [
+            (&a, &b),
+            (&b, &int("1000000007")),
+            (&a, &(&b + &BigInt::one())),
+        ] {
+            let (q, r) = a.div_rem(b).unwrap();
+            assert!(!r.is_negative() && r < *b);
+            assert_eq!(&(&q * b) + &r, *a);
+        }
+        // Compare against u128 division for a spread of limb patterns, which takes every
+        // branch of the quotient digit estimate
+        let mut seed = 0x2545_f491_4f6c_dd1d_u128;
+        for _ in 0..2000 {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let a = seed >> (seed % 64);
+            let b = (seed >> 64) >> (seed % 61) | 1;
+            let (q, r) = int(&a.to_string()).div_rem(&int(&b.to_string())).unwrap();
+            assert_eq!(
+                (q.to_string(), r.to_string()),
+                ((a / b).to_string(), (a % b).to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn powers() {
+        assert_eq!(
+            BigInt::from(2).pow(100).to_string(),
+            "1267650600228229401496703205376"
+        );
+        assert_eq!(BigInt::from(-3).pow(3).to_string(), "-27");
+        assert_eq!(BigInt::from(-3).pow(4).to_string(), "81");
+        assert_eq!(BigInt::from(5).pow(0).to_string(), "1");
+        assert_eq!(BigInt::zero().pow(0).to_string(), "1");
+        // Big enough for Karatsuba multiplication
+        let big = BigInt::from(7).pow(2000);
+        assert_eq!(&big * &big, BigInt::from(7).pow(4000));
+        assert_eq!(big.pow(2).div_rem(&big).unwrap().0, big);
+    }
+
+    #[test]
+    fn gcd_and_lcm() {
+        assert_eq!(BigInt::from(12).gcd(&BigInt::from(18)).to_string(), "6");
+        assert_eq!(BigInt::from(-12).gcd(&BigInt::from(18)).to_string(), "6");
+        assert_eq!(BigInt::from(0).gcd(&BigInt::from(-5)).to_string(), "5");
+        assert_eq!(BigInt::from(4).lcm(&BigInt::from(-6)).to_string(), "12");
+        let a = &BigInt::from(2).pow(300) * &BigInt::from(3).pow(200);
+        let b = BigInt::from(6).pow(250);
+        assert_eq!(
+            a.gcd(&b),
+            &BigInt::from(2).pow(250) * &BigInt::from(3).pow(200)
+        );
+    }
+
+    #[test]
+    fn factorials() {
+        assert_eq!(BigInt::from(0).factorial(1).to_string(), "1");
+        assert_eq!(
+            BigInt::from(20).factorial(1).to_string(),
+            "2432902008176640000"
+        );
+        assert_eq!(BigInt::from(9).factorial(2).to_string(), "945");
+        assert_eq!(BigInt::from(10).factorial(2).to_string(), "3840");
+        let big = BigInt::from(1000).factorial(1);
+        assert_eq!(big.to_string().len(), 2568);
+        assert_eq!(
+            big.div_rem(&BigInt::from(999).factorial(1)).unwrap().0,
+            BigInt::from(1000)
+        );
+    }
+}
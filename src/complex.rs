@@ -0,0 +1,230 @@
+use std::{
+    f64::consts::FRAC_PI_2,
+    fmt,
+    ops::{Add, Div, Mul, Neg, Sub},
+};
+
+/// A complex number `re + im·i`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub const I: Self = Self { re: 0.0, im: 1.0 };
+
+    pub fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    pub fn from_polar(r: f64, theta: f64) -> Self {
+        Self::new(r * theta.cos(), r * theta.sin())
+    }
+
+    /// The modulus `|z|`
+    pub fn abs(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    /// The argument in `(-π, π]`
+    pub fn arg(self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    pub fn scale(self, factor: f64) -> Self {
+        Self::new(self.re * factor, self.im * factor)
+    }
+
+    /// Integer powers use repeated squaring, so results like `i²` come out exact
+    pub fn powi(self, exponent: i32) -> Self {
+        let mut result = Self::new(1.0, 0.0);
+        let mut base = self;
+        let mut n = exponent.unsigned_abs();
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            n >>= 1;
+        }
+        if exponent < 0 {
+            Self::new(1.0, 0.0) / result
+        } else {
+            result
+        }
+    }
+
+    pub fn pow(self, exponent: Self) -> Self {
+        if exponent.im == 0.0 && exponent.re.fract() == 0.0 && exponent.re.abs() <= 1024.0 {
+            return self.powi(exponent.re as i32);
+        }
+        if self.re == 0.0 && self.im == 0.0 {
+            return Self::new(0.0, 0.0);
+        }
+        (exponent * self.ln()).exp()
+    }
+
+    pub fn exp(self) -> Self {
+        Self::from_polar(self.re.exp(), self.im)
+    }
+
+    pub fn ln(self) -> Self {
+        Self::new(self.abs().ln(), self.arg())
+    }
+
+    /// The principal square root, computed without trig so `√-1` is exactly `i`
+    pub fn sqrt(self) -> Self {
+        let r = self.abs();
+        let re = ((r + self.re) / 2.0).sqrt();
+        let im = ((r - self.re) / 2.0).sqrt();
+        Self::new(re, if self.im.is_sign_negative() { -im } else { im })
+    }
+
+    pub fn cbrt(self) -> Self {
+        Self::from_polar(self.abs().cbrt(), self.arg() / 3.0)
+    }
+
+    pub fn sin(self) -> Self {
+        Self::new(
+            self.re.sin() * self.im.cosh(),
+            self.re.cos() * self.im.sinh(),
+        )
+    }
+
+    pub fn cos(self) -> Self {
+        Self::new(
+            self.re.cos() * self.im.cosh(),
+            -self.re.sin() * self.im.sinh(),
+        )
+    }
+
+    pub fn tan(self) -> Self {
+        self.sin() / self.cos()
+    }
+
+    pub fn sinh(self) -> Self {
+        Self::new(
+            self.re.sinh() * self.im.cos(),
+            self.re.cosh() * self.im.sin(),
+        )
+    }
+
+    pub fn cosh(self) -> Self {
+        Self::new(
+            self.re.cosh() * self.im.cos(),
+            self.re.sinh() * self.im.sin(),
+        )
+    }
+
+    pub fn tanh(self) -> Self {
+        self.sinh() / self.cosh()
+    }
+
+    pub fn asin(self) -> Self {
+        let one = Self::from(1.0);
+        // -i·ln(iz + √(1 - z²))
+        -Self::I * (Self::I * self + (one - self * self).sqrt()).ln()
+    }
+
+    pub fn acos(self) -> Self {
+        Self::from(FRAC_PI_2) - self.asin()
+    }
+
+    pub fn atan(self) -> Self {
+        let one = Self::from(1.0);
+        // i/2·(ln(1 - iz) - ln(1 + iz))
+        Self::new(0.0, 0.5) * ((one - Self::I * self).ln() - (one + Self::I * self).ln())
+    }
+
+    pub fn asinh(self) -> Self {
+        (self + (self * self + Self::from(1.0)).sqrt()).ln()
+    }
+
+    pub fn acosh(self) -> Self {
+        let one = Self::from(1.0);
+        (self + (self + one).sqrt() * (self - one).sqrt()).ln()
+    }
+
+    pub fn atanh(self) -> Self {
+        let one = Self::from(1.0);
+        ((one + self).ln() - (one - self).ln()).scale(0.5)
+    }
+}
+
+impl Neg for Complex {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.re, -self.im)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+impl Div for Complex {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        let denom = other.re * other.re + other.im * other.im;
+        Self::new(
+            (self.re * other.re + self.im * other.im) / denom,
+            (self.im * other.re - self.re * other.im) / denom,
+        )
+    }
+}
+
+impl From<f64> for Complex {
+    fn from(re: f64) -> Self {
+        Self::new(re, 0.0)
+    }
+}
+
+/// Shows `a + bi` normally, or the polar form `r∠θ` with the alternate flag (`{:#}`)
+impl fmt::Display for Complex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return write!(f, "{}∠{}", self.abs(), self.arg());
+        }
+
+        let imaginary = match self.im.abs() {
+            1.0 => "i".to_string(),
+            im => format!("{}i", im),
+        };
+        match (self.re, self.im.is_sign_negative()) {
+            (0.0, false) => write!(f, "{}", imaginary),
+            (0.0, true) => write!(f, "-{}", imaginary),
+            (re, false) => write!(f, "{} + {}", re, imaginary),
+            (re, true) => write!(f, "{} - {}", re, imaginary),
+        }
+    }
+}
@@ -0,0 +1,597 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{BinaryOp, Captured, Function, Node, NodeType, Scope, UnaryOp, Value};
+
+/// How deeply calls to user functions are inlined, so recursive functions give an error
+/// instead of overflowing the stack
+const MAX_INLINE_DEPTH: u32 = 64;
+
+/// Differentiates a function with respect to one of its arguments, or its first argument when
+/// `var` is `None`. Native functions are differentiated through a call with a fresh argument
+/// in `scope`.
+pub fn derive_function(
+    name: &str,
+    function: Value,
+    var: Option<&str>,
+    scope: &Rc<Scope>,
+) -> Result<Value, DeriveError> {
+    let (arg_names, body, scope) = match function {
+        Value::Function(Function {
+            arg_names,
+            body,
+            scope,
+            ..
+        }) => (arg_names, *body, scope),
+        Value::NativeFunction(_) => {
+            let arg: Rc<str> = "x".into();
+            let body = call(name, vec![ident(&arg)]);
+            (vec![arg], body, Captured::new(scope))
+        }
+        value => return Err(format!("{} is not a function", value).into()),
+    };
+
+    let var: Rc<str> = match var {
+        Some(var) if arg_names.iter().any(|arg| **arg == *var) => var.into(),
+        Some(var) => return Err(format!("'{}' is not an argument of {}", var, name).into()),
+        None => match arg_names.first() {
+            Some(var) => var.clone(),
+            None => return Err(format!("{} has no arguments to differentiate by", name).into()),
+        },
+    };
+
+    let body = derive(&body, &var, &arg_names, &scope.scope())?;
+    Ok(Value::Function(Function {
+        name: format!("{}'", name).into(),
+        arg_names,
+        body: Box::new(body),
+        scope,
+    }))
+}
+
+/// The derivative of `node` with respect to `var`, treating every other variable as a constant.
+/// The names in `bound` are variables too, like the other arguments of a function, while any
+/// other name has to be defined in `scope`.
+pub fn derive(
+    node: &Node,
+    var: &str,
+    bound: &[Rc<str>],
+    scope: &Scope,
+) -> Result<Node, DeriveError> {
+    Deriver {
+        var,
+        bound: bound.to_vec(),
+        scope,
+        depth: 0,
+    }
+    .derive(node)
+}
+
+/// Why something can't be differentiated
+#[derive(Debug)]
+pub enum DeriveError {
+    /// A name that isn't defined, so it can't be treated as a constant
+    Undefined(Rc<str>),
+    Invalid(String),
+}
+
+impl From<String> for DeriveError {
+    fn from(reason: String) -> Self {
+        Self::Invalid(reason)
+    }
+}
+
+struct Deriver<'a> {
+    var: &'a str,
+    /// Variables other than `var`, like sum indices, which aren't in `scope`
+    bound: Vec<Rc<str>>,
+    scope: &'a Scope,
+    depth: u32,
+}
+
+impl Deriver<'_> {
+    fn derive(&mut self, node: &Node) -> Result<Node, DeriveError> {
+        match &node.ty {
+            NodeType::Number(_) => Ok(num(0.0)),
+            NodeType::Identifier(name) if **name == *self.var => Ok(num(1.0)),
+            NodeType::Identifier(name) => self.constant(name),
+            NodeType::Unary(op, u) => self.unary(*op, u),
+            NodeType::Binary(u, op, v) => self.binary(u, *op, v),
+            NodeType::Call(name, args) => self.call(name, args),
+            NodeType::Vector(nodes) => Ok(Node::from(NodeType::Vector(
+                nodes
+                    .iter()
+                    .map(|node| self.derive(node))
+                    .collect::<Result<_, _>>()?,
+            ))),
+            NodeType::Matrix(rows) => Ok(Node::from(NodeType::Matrix(
+                rows.iter()
+                    .map(|row| row.iter().map(|node| self.derive(node)).collect())
+                    .collect::<Result<_, _>>()?,
+            ))),
+            NodeType::Index(u, indices) => Ok(Node::from(NodeType::Index(
+                Box::new(self.derive(u)?),
+                indices.clone(),
+            ))),
+            // Piecewise functions are differentiated piece by piece
+            NodeType::If(condition, then, otherwise) => Ok(Node::from(NodeType::If(
+                condition.clone(),
+                Box::new(self.derive(then)?),
+                match otherwise {
+                    Some(otherwise) => Some(Box::new(self.derive(otherwise)?)),
+                    None => None,
+                },
+            ))),
+            NodeType::Sum(index, _, _, _) if **index == *self.var => Ok(num(0.0)),
+            NodeType::Sum(index, lower, upper, body) => Ok(Node::from(NodeType::Sum(
+                index.clone(),
+                lower.clone(),
+                upper.clone(),
+                Box::new(self.derive_bound(index, body)?),
+            ))),
+            NodeType::Product(index, _, _, _) if **index == *self.var => Ok(num(0.0)),
+            // (Π f)' = Π f · Σ f'/f
+            NodeType::Product(index, lower, upper, body) => Ok(mul(
+                node.clone(),
+                Node::from(NodeType::Sum(
+                    index.clone(),
+                    lower.clone(),
+                    upper.clone(),
+                    Box::new(div(self.derive_bound(index, body)?, (**body).clone())),
+                )),
+            )),
+            NodeType::Integral(t, lower, upper, body) => self.integral(t, lower, upper, body),
+            NodeType::Derivative(u, var) => {
+                let inner = Deriver {
+                    var,
+                    bound: self.bound.clone(),
+                    scope: self.scope,
+                    depth: self.depth,
+                }
+                .derive(&self.inline(u, var)?)?;
+                self.derive_bound(var, &inner)
+            }
+            NodeType::Block(statements) => match flatten(statements) {
+                Some(flat) => self.derive(&flat),
+                None => Err(format!("cannot differentiate {}", node).into()),
+            },
+            _ => Err(format!("cannot differentiate {}", node).into()),
+        }
+    }
+
+    fn unary(&mut self, op: UnaryOp, u: &Node) -> Result<Node, DeriveError> {
+        let du = self.derive(u)?;
+        let u = u.clone();
+        Ok(match op {
+            UnaryOp::Pos => du,
+            UnaryOp::Neg => neg(du),
+            // |u|' = u/|u| · u'
+            UnaryOp::Abs => mul(div(u.clone(), unary(UnaryOp::Abs, u)), du),
+            // Steps are flat everywhere they're differentiable
+            UnaryOp::Floor | UnaryOp::Ceil | UnaryOp::Round => num(0.0),
+            UnaryOp::Sqrt => div(du, mul(num(2.0), unary(UnaryOp::Sqrt, u))),
+            UnaryOp::Cbrt => div(du, mul(num(3.0), pow(unary(UnaryOp::Cbrt, u), num(2.0)))),
+            UnaryOp::Fort => div(du, mul(num(4.0), pow(unary(UnaryOp::Fort, u), num(3.0)))),
+            UnaryOp::Degree => unary(UnaryOp::Degree, du),
+            // u!' = u! · ψ(u + 1) · u'
+            UnaryOp::Fact => mul(
+                mul(
+                    unary(UnaryOp::Fact, u.clone()),
+                    call("digamma", vec![add(u, num(1.0))]),
+                ),
+                du,
+            ),
+            UnaryOp::DoubleFact => {
+                return Err(format!(
+                    "{} is only defined on integers, so it has no derivative",
+                    NodeType::Unary(op, Box::new(u))
+                )
+                .into())
+            }
+            UnaryOp::Not => {
+                return Err(
+                    format!("cannot differentiate {}", NodeType::Unary(op, Box::new(u))).into(),
+                )
+            }
+        })
+    }
+
+    fn binary(&mut self, u: &Node, op: BinaryOp, v: &Node) -> Result<Node, DeriveError> {
+        let (du, dv) = (self.derive(u)?, self.derive(v)?);
+        let (u, v) = (u.clone(), v.clone());
+        Ok(match op {
+            BinaryOp::Add => add(du, dv),
+            BinaryOp::Sub => sub(du, dv),
+            // The product rule, keeping the order of the factors for cross products
+            BinaryOp::Mul | BinaryOp::Dot | BinaryOp::Cross => {
+                add(binary(du, op, v.clone()), binary(u, op, dv))
+            }
+            // The quotient rule
+            BinaryOp::Div => div(sub(mul(du, v.clone()), mul(u, dv)), pow(v, num(2.0))),
+            // u mod v = u - v⌊u/v⌋, where the floor is flat
+            BinaryOp::Mod => sub(du, mul(dv, unary(UnaryOp::Floor, div(u, v)))),
+            BinaryOp::Pow if !self.depends(&v) => mul(mul(v.clone(), pow(u, sub(v, num(1.0)))), du),
+            // ln e is 1
+            BinaryOp::Pow if matches!(&u.ty, NodeType::Identifier(name) if &**name == "e") => {
+                mul(pow(u, v), dv)
+            }
+            BinaryOp::Pow if !self.depends(&u) => {
+                mul(mul(pow(u.clone(), v), call("ln", vec![u])), dv)
+            }
+            // (u^v)' = u^v · (v' ln u + v u'/u)
+            BinaryOp::Pow => mul(
+                pow(u.clone(), v.clone()),
+                add(mul(dv, call("ln", vec![u.clone()])), div(mul(v, du), u)),
+            ),
+            _ => {
+                return Err(format!(
+                    "cannot differentiate {}",
+                    NodeType::Binary(Box::new(u), op, Box::new(v))
+                )
+                .into())
+            }
+        })
+    }
+
+    fn call(&mut self, name: &Rc<str>, args: &[Node]) -> Result<Node, DeriveError> {
+        match self.scope.get(name) {
+            Some(Value::Function(Function {
+                arg_names, body, ..
+            })) => {
+                if self.depth == MAX_INLINE_DEPTH {
+                    return Err(
+                        format!("cannot differentiate the recursive function {}", name).into(),
+                    );
+                }
+                if args.len() != arg_names.len() {
+                    return Err(format!(
+                        "{} takes {} arguments, but was given {}",
+                        name,
+                        arg_names.len(),
+                        args.len()
+                    )
+                    .into());
+                }
+
+                let bindings = arg_names.into_iter().zip(args.iter().cloned()).collect();
+                self.depth += 1;
+                let result = self.derive(&substitute(&body, &bindings));
+                self.depth -= 1;
+                result
+            }
+            Some(Value::NativeFunction(_)) => {
+                let [u] = args else {
+                    return Err(
+                        format!("cannot differentiate {}", call(name, args.to_vec())).into(),
+                    );
+                };
+                let du = self.derive(u)?;
+                let u = u.clone();
+                let f = |name: &str, u: Node| call(name, vec![u]);
+                let derivative = match &**name {
+                    "sin" => f("cos", u),
+                    "cos" => neg(f("sin", u)),
+                    "tan" => div(num(1.0), pow(f("cos", u), num(2.0))),
+                    "asin" => div(num(1.0), f("sqrt", sub(num(1.0), pow(u, num(2.0))))),
+                    "acos" => neg(div(num(1.0), f("sqrt", sub(num(1.0), pow(u, num(2.0)))))),
+                    "atan" => div(num(1.0), add(num(1.0), pow(u, num(2.0)))),
+                    "sinh" => f("cosh", u),
+                    "cosh" => f("sinh", u),
+                    "tanh" => div(num(1.0), pow(f("cosh", u), num(2.0))),
+                    "asinh" => div(num(1.0), f("sqrt", add(pow(u, num(2.0)), num(1.0)))),
+                    "acosh" => div(
+                        num(1.0),
+                        mul(
+                            f("sqrt", sub(u.clone(), num(1.0))),
+                            f("sqrt", add(u, num(1.0))),
+                        ),
+                    ),
+                    "atanh" => div(num(1.0), sub(num(1.0), pow(u, num(2.0)))),
+                    "exp" => f("exp", u),
+                    "ln" => div(num(1.0), u),
+                    "sqrt" => div(num(1.0), mul(num(2.0), f("sqrt", u))),
+                    "cbrt" => div(num(1.0), mul(num(3.0), pow(f("cbrt", u), num(2.0)))),
+                    "abs" => div(u.clone(), f("abs", u)),
+                    "floor" | "ceil" | "round" | "trunc" => num(0.0),
+                    "fract" | "float" => num(1.0),
+                    "gamma" => mul(f("gamma", u.clone()), f("digamma", u)),
+                    "lgamma" => f("digamma", u),
+                    // These are linear, so they pass straight through to the derivative
+                    "re" | "im" | "conj" => return Ok(f(name, du)),
+                    _ => return Err(format!("cannot differentiate {}", name).into()),
+                };
+                Ok(mul(derivative, du))
+            }
+            _ => Err(format!("'{}' is not a function", name).into()),
+        }
+    }
+
+    /// The Leibniz rule: `d/dx ∫ₐᵇ f dt = f(b) b' - f(a) a' + ∫ₐᵇ ∂f/∂x dt`
+    fn integral(
+        &mut self,
+        t: &Rc<str>,
+        lower: &Node,
+        upper: &Node,
+        body: &Node,
+    ) -> Result<Node, DeriveError> {
+        let at = |bound: &Node| {
+            let bindings = HashMap::from([(t.clone(), bound.clone())]);
+            substitute(body, &bindings)
+        };
+        let boundary = sub(
+            mul(at(upper), self.derive(upper)?),
+            mul(at(lower), self.derive(lower)?),
+        );
+        if **t == *self.var {
+            return Ok(boundary);
+        }
+
+        let inside = self.derive_bound(t, body)?;
+        if as_number(&inside) == Some(0.0) {
+            return Ok(boundary);
+        }
+        Ok(add(
+            boundary,
+            Node::from(NodeType::Integral(
+                t.clone(),
+                Box::new(lower.clone()),
+                Box::new(upper.clone()),
+                Box::new(inside),
+            )),
+        ))
+    }
+
+    /// Differentiates `node`, where `name` is a variable that doesn't change with `var`
+    fn derive_bound(&mut self, name: &Rc<str>, node: &Node) -> Result<Node, DeriveError> {
+        self.bound.push(name.clone());
+        let result = self.derive(node);
+        self.bound.pop();
+        result
+    }
+
+    /// The derivative of a name other than the variable, which is a constant if it's a value.
+    /// An undefined name is an error instead of 0, since it's most likely a typo.
+    fn constant(&self, name: &Rc<str>) -> Result<Node, DeriveError> {
+        if self.bound.contains(name) {
+            return Ok(num(0.0));
+        }
+        match self.scope.get(name) {
+            Some(Value::Function(_) | Value::NativeFunction(_)) => Err(format!(
+                "{} is a function, so it needs an argument, like {}({})",
+                name, name, self.var
+            )
+            .into()),
+            Some(_) => Ok(num(0.0)),
+            None => Err(DeriveError::Undefined(name.clone())),
+        }
+    }
+
+    /// Turns the operand of a nested `d/dx` into an expression in `var`, so `d/dx d/dx f`
+    /// differentiates the body of `f`
+    fn inline(&self, node: &Node, var: &Rc<str>) -> Result<Node, DeriveError> {
+        if let NodeType::Identifier(name) = &node.ty {
+            match self.scope.get(name) {
+                Some(Value::Function(Function {
+                    arg_names, body, ..
+                })) => {
+                    let Some(arg) = arg_names.first() else {
+                        return Err(format!("{} has no arguments to differentiate by", name).into());
+                    };
+                    let bindings = HashMap::from([(arg.clone(), ident(var))]);
+                    return Ok(substitute(&body, &bindings));
+                }
+                Some(Value::NativeFunction(_)) => return Ok(call(name, vec![ident(var)])),
+                _ => {}
+            }
+        }
+        Ok(node.clone())
+    }
+
+    /// Whether `node` can change with the variable
+    fn depends(&self, node: &Node) -> bool {
+        match &node.ty {
+            NodeType::Number(_) | NodeType::Bool(_) => false,
+            NodeType::Identifier(name) => **name == *self.var,
+            NodeType::Unary(_, u) => self.depends(u),
+            NodeType::Binary(u, _, v) => self.depends(u) || self.depends(v),
+            // Calls to user functions might read the variable from the scope
+            _ => true,
+        }
+    }
+}
+
+/// Replaces the variables in `bindings` with their nodes, leaving variables that are bound
+/// again inside sums, products and integrals alone
+pub fn substitute(node: &Node, bindings: &HashMap<Rc<str>, Node>) -> Node {
+    let without = |name: &Rc<str>| {
+        let mut bindings = bindings.clone();
+        bindings.remove(name);
+        bindings
+    };
+    // Substitutes into the bounds, and into the body without the bound variable
+    let bound = |name: &Rc<str>, body: &Node| {
+        let inner = without(name);
+        node.map_children(|child| {
+            if std::ptr::eq(child, body) {
+                substitute(child, &inner)
+            } else {
+                substitute(child, bindings)
+            }
+        })
+    };
+
+    match &node.ty {
+        NodeType::Identifier(name) => match bindings.get(name) {
+            Some(replacement) => replacement.clone(),
+            None => node.clone(),
+        },
+        NodeType::Sum(index, _, _, body) | NodeType::Product(index, _, _, body) => {
+            bound(index, body)
+        }
+        NodeType::Integral(var, _, _, body) => bound(var, body),
+        NodeType::Lambda(args, body) => {
+            let mut inner = bindings.clone();
+            for arg in args {
+                inner.remove(arg);
+            }
+            node.map_children(|_| substitute(body, &inner))
+        }
+        // Assignments in a block hide the bindings from the statements after them
+        NodeType::Block(statements) => {
+            let mut inner = bindings.clone();
+            let statements = statements
+                .iter()
+                .map(|statement| {
+                    let statement = substitute(statement, &inner);
+                    if let NodeType::Assignment(name, _) | NodeType::FnDef(name, ..) = &statement.ty
+                    {
+                        inner.remove(name);
+                    }
+                    statement
+                })
+                .collect();
+            Node {
+                ty: NodeType::Block(statements),
+                range: node.range.clone(),
+            }
+        }
+        _ => node.map_children(|child| substitute(child, bindings)),
+    }
+}
+
+/// The last statement of a block with the variables assigned before it substituted in, so
+/// `{ a = x²; a + 1 }` becomes `x² + 1`. Blocks that do anything but assign variables before
+/// the last statement can't be written as one expression.
+pub fn flatten(statements: &[Node]) -> Option<Node> {
+    let (last, assignments) = statements.split_last()?;
+    let mut bindings = HashMap::new();
+    for statement in assignments {
+        let NodeType::Assignment(name, value) = &statement.ty else {
+            return None;
+        };
+        let value = substitute(value, &bindings);
+        bindings.insert(name.clone(), value);
+    }
+    Some(substitute(last, &bindings))
+}
+
+// Constructors that skip the obvious identities, so derivatives don't fill up with `0 ×` and `+ 0`
+
+fn as_number(node: &Node) -> Option<f64> {
+    match &node.ty {
+        NodeType::Number(x) => x.parse().ok(),
+        _ => None,
+    }
+}
+
+pub fn num(x: f64) -> Node {
+    if x < 0.0 {
+        return neg(num(-x));
+    }
+    Node::from(NodeType::Number(x.to_string().into()))
+}
+
+pub fn ident(name: &str) -> Node {
+    Node::from(NodeType::Identifier(name.into()))
+}
+
+pub fn call(name: &str, args: Vec<Node>) -> Node {
+    Node::from(NodeType::Call(name.into(), args))
+}
+
+pub fn unary(op: UnaryOp, u: Node) -> Node {
+    Node::from(NodeType::Unary(op, Box::new(u)))
+}
+
+pub fn binary(u: Node, op: BinaryOp, v: Node) -> Node {
+    match op {
+        BinaryOp::Mul => mul(u, v),
+        _ => Node::from(NodeType::Binary(Box::new(u), op, Box::new(v))),
+    }
+}
+
+pub fn neg(u: Node) -> Node {
+    match u.ty {
+        NodeType::Unary(UnaryOp::Neg, u) => *u,
+        _ if as_number(&u) == Some(0.0) => u,
+        _ => unary(UnaryOp::Neg, u),
+    }
+}
+
+pub fn add(u: Node, v: Node) -> Node {
+    match (as_number(&u), as_number(&v)) {
+        (Some(a), Some(b)) => num(a + b),
+        (Some(0.0), _) => v,
+        (_, Some(0.0)) => u,
+        _ => match v.ty {
+            NodeType::Unary(UnaryOp::Neg, v) => sub(u, *v),
+            ty => Node::from(NodeType::Binary(
+                Box::new(u),
+                BinaryOp::Add,
+                Box::new(Node::from(ty)),
+            )),
+        },
+    }
+}
+
+pub fn sub(u: Node, v: Node) -> Node {
+    match (as_number(&u), as_number(&v)) {
+        (Some(a), Some(b)) => num(a - b),
+        (Some(0.0), _) => neg(v),
+        (_, Some(0.0)) => u,
+        _ => match v.ty {
+            NodeType::Unary(UnaryOp::Neg, v) => add(u, *v),
+            ty => Node::from(NodeType::Binary(
+                Box::new(u),
+                BinaryOp::Sub,
+                Box::new(Node::from(ty)),
+            )),
+        },
+    }
+}
+
+pub fn mul(u: Node, v: Node) -> Node {
+    match (as_number(&u), as_number(&v)) {
+        (Some(a), Some(b)) => num(a * b),
+        (Some(a), _) | (_, Some(a)) if a == 0.0 => num(0.0),
+        (Some(1.0), _) => v,
+        (_, Some(1.0)) => u,
+        // Constants go in front, like in 2x
+        (None, Some(_)) => mul(v, u),
+        _ => match (u.ty, v.ty) {
+            // Gather constants at the front, so 2 × 3x is 6x and x × 2y is 2x × y
+            (u, NodeType::Binary(c, BinaryOp::Mul, rest)) if as_number(&c).is_some() => {
+                mul(mul(*c, Node::from(u)), *rest)
+            }
+            (NodeType::Unary(UnaryOp::Neg, u), v) => neg(mul(*u, Node::from(v))),
+            (u, NodeType::Unary(UnaryOp::Neg, v)) => neg(mul(Node::from(u), *v)),
+            (u, v) => Node::from(NodeType::Binary(
+                Box::new(Node::from(u)),
+                BinaryOp::Mul,
+                Box::new(Node::from(v)),
+            )),
+        },
+    }
+}
+
+pub fn div(u: Node, v: Node) -> Node {
+    match (as_number(&u), as_number(&v)) {
+        (Some(0.0), _) => num(0.0),
+        (_, Some(1.0)) => u,
+        _ => match (u.ty, v.ty) {
+            (NodeType::Unary(UnaryOp::Neg, u), v) => neg(div(*u, Node::from(v))),
+            (u, v) => Node::from(NodeType::Binary(
+                Box::new(Node::from(u)),
+                BinaryOp::Div,
+                Box::new(Node::from(v)),
+            )),
+        },
+    }
+}
+
+pub fn pow(u: Node, v: Node) -> Node {
+    match as_number(&v) {
+        Some(0.0) => num(1.0),
+        Some(1.0) => u,
+        _ => Node::from(NodeType::Binary(Box::new(u), BinaryOp::Pow, Box::new(v))),
+    }
+}
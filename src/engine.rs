@@ -0,0 +1,93 @@
+use std::{fmt, ops::RangeInclusive, rc::Rc};
+
+use crate::{AmiError, Args, Interpreter, Lexer, NativeError, NativeFunction, Parser, Value};
+
+/// Runs ami code, keeping variables and functions from one run to the next
+#[derive(Default)]
+pub struct Engine {
+    interpreter: Interpreter,
+}
+
+/// Details about a run for debugging, which an [`Engine`] passes to its
+/// [`on_diagnostic`](Engine::on_diagnostic) callback
+#[derive(Debug, Clone, PartialEq)]
+pub enum Diagnostic {
+    /// The tokens of the source, separated by spaces
+    Tokens(String),
+    /// The syntax tree of the source
+    Ast(String),
+    /// The estimated error of a numerical integral
+    IntegralError(f64),
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tokens(tokens) => write!(f, "tokens: {}", tokens),
+            Self::Ast(ast) => write!(f, "AST: {}", ast),
+            Self::IntegralError(error) => write!(f, "integral error estimate: {:e}", error),
+        }
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `source`, returning the value of its last statement
+    pub fn eval(&mut self, source: &str) -> Result<Value, AmiError> {
+        let tokens = Lexer::new(source).lex()?;
+        self.interpreter.report(|| {
+            Diagnostic::Tokens(
+                tokens
+                    .iter()
+                    .map(|t| t.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            )
+        });
+
+        let ast = Parser::new(tokens).parse()?;
+        self.interpreter.report(|| Diagnostic::Ast(ast.to_string()));
+
+        self.interpreter.run(ast)
+    }
+
+    /// Calls `callback` with the details of each run, like its tokens and syntax tree
+    pub fn on_diagnostic(&mut self, callback: impl Fn(&Diagnostic) + 'static) {
+        self.interpreter.diagnostics = Some(Rc::new(callback));
+    }
+
+    /// Checks that `source` is valid syntax without running it
+    pub fn check(source: &str) -> Result<(), AmiError> {
+        let tokens = Lexer::new(source).lex()?;
+        Parser::new(tokens).parse()?;
+        Ok(())
+    }
+
+    /// The value of a variable or function, if it's defined
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.interpreter.scope.get(name)
+    }
+
+    pub fn set(&mut self, name: &str, value: impl Into<Value>) {
+        self.interpreter.scope.set(name.into(), value.into());
+    }
+
+    /// Adds a function written in Rust, which is called with `arity` arguments
+    pub fn register(
+        &mut self,
+        name: &str,
+        arity: RangeInclusive<usize>,
+        function: impl Fn(&mut Interpreter, &Args) -> Result<Value, NativeError> + 'static,
+    ) {
+        let function = NativeFunction::new(name, arity, function);
+        self.set(name, Value::NativeFunction(function));
+    }
+}
+
+/// Runs `source` on its own, returning the value of its last statement
+pub fn eval(source: &str) -> Result<Value, AmiError> {
+    Engine::new().eval(source)
+}
@@ -1,7 +1,45 @@
-use std::ops::Range;
+use ariadne::{Color, Config, IndexType, Label, Report, ReportKind, Source};
+use std::{error, fmt, ops::Range};
 
+#[derive(Debug, Clone)]
 pub struct AmiError {
     pub msg: String,
     pub reason: String,
     pub range: Range<usize>,
 }
+
+impl AmiError {
+    /// Draws the error under the line of `source` it points at, the way the command line
+    /// shows it
+    pub fn render(&self, source: &str, color: bool) -> String {
+        let config = Config::default()
+            .with_color(color)
+            .with_index_type(IndexType::Byte);
+        let mut label = Label::new(self.range.clone()).with_message(&self.reason);
+        if color {
+            label = label.with_color(Color::Red);
+        }
+
+        let mut output = Vec::new();
+        Report::build(ReportKind::Error, self.range.clone())
+            .with_config(config)
+            .with_message(&self.msg)
+            .with_label(label)
+            .finish()
+            .write(Source::from(source), &mut output)
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+}
+
+impl fmt::Display for AmiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.msg.is_empty(), self.reason.is_empty()) {
+            (false, false) => write!(f, "{}: {}", self.msg, self.reason),
+            (true, _) => write!(f, "{}", self.reason),
+            (_, true) => write!(f, "{}", self.msg),
+        }
+    }
+}
+
+impl error::Error for AmiError {}
@@ -1,15 +1,25 @@
-use std::ops::Range;
+use std::{ops::Range, rc::Rc, slice};
 
-use crate::{AmiError, BinaryOp, Node, NodeType, Scope, UnaryOp, Value};
+use crate::{
+    arithmetic, derivative, derivative::DeriveError, math, native::plural, simplify, solver,
+    solver::Roots, AmiError, Args, BigInt, BinaryOp, Captured, Complex, Diagnostic, Function,
+    Matrix, NativeError, NativeFunction, Node, NodeType, Scope, UnaryOp, Value,
+};
 
-pub struct Interpreter<'a> {
-    pub scope: Scope<'a>,
+pub struct Interpreter {
+    pub(crate) scope: Rc<Scope>,
+    /// Where to send extra details, like the error estimates of integrals
+    pub(crate) diagnostics: Option<Rc<DiagnosticCallback>>,
+    /// How many user function calls deep this is
+    depth: u32,
 }
 
-impl Default for Interpreter<'_> {
+impl Default for Interpreter {
     fn default() -> Self {
         let mut interpreter = Self {
-            scope: Scope::default(),
+            scope: Rc::new(Scope::default()),
+            diagnostics: None,
+            depth: 0,
         };
         interpreter.add_builtins();
         interpreter
@@ -17,18 +27,97 @@ impl Default for Interpreter<'_> {
 }
 
 type RuntimeError = Result<Value, AmiError>;
+type DiagnosticCallback = dyn Fn(&Diagnostic);
+
+/// How small a term has to be, relative to the result, for an infinite series to stop
+const SERIES_TOLERANCE: f64 = f64::EPSILON;
+/// How closely two extrapolations of a slowly converging series have to agree to be the result
+const EXTRAPOLATION_TOLERANCE: f64 = 1e-12;
+const MAX_SERIES_TERMS: u32 = 1 << 20;
+/// How deeply user functions can call each other, so runaway recursion is an error instead of
+/// a stack overflow
+const MAX_CALL_DEPTH: u32 = 10_000;
+/// How much stack has to be left before `visit` moves onto a new segment of this size
+const STACK_RED_ZONE: usize = 128 * 1024;
+const STACK_SEGMENT: usize = 4 * 1024 * 1024;
+/// The largest relative error estimate an integral can have and still count as converged
+const INTEGRAL_CONVERGENCE: f64 = 1e-6;
+
+impl Interpreter {
+    /// An interpreter for a nested scope, like the body of a sum
+    fn child(&self, scope: Rc<Scope>) -> Interpreter {
+        Interpreter {
+            scope,
+            diagnostics: self.diagnostics.clone(),
+            depth: self.depth,
+        }
+    }
+
+    /// Passes a diagnostic to the callback, only building it when there is one
+    pub(crate) fn report(&self, diagnostic: impl FnOnce() -> Diagnostic) {
+        if let Some(callback) = &self.diagnostics {
+            callback(&diagnostic());
+        }
+    }
 
-impl Interpreter<'_> {
     fn error<T>(&self, msg: String, reason: String, range: Range<usize>) -> Result<T, AmiError> {
         Err(AmiError { msg, reason, range })
     }
 
-    pub fn run(&mut self, ast: Node) -> RuntimeError {
+    fn undefined<T>(&self, name: &str, range: Range<usize>) -> Result<T, AmiError> {
+        let reason = match self.scope.similar(name) {
+            Some(similar) => format!("'{}' is not defined, did you mean '{}'?", name, similar),
+            None => format!("'{}' is not defined", name),
+        };
+        self.error(format!("undefined name '{}'", name), reason, range)
+    }
+
+    /// Why a derivative at `range` can't be taken
+    fn derivative_error<T>(&self, error: DeriveError, range: Range<usize>) -> Result<T, AmiError> {
+        match error {
+            DeriveError::Undefined(name) => self.undefined(&name, range),
+            DeriveError::Invalid(reason) => {
+                self.error("invalid derivative".to_string(), reason, range)
+            }
+        }
+    }
+
+    /// Looks up a name, differentiating the function for each trailing prime, as in `f''`
+    fn resolve(&self, name: &str) -> Result<Option<Value>, DeriveError> {
+        if let Some(value) = self.scope.get(name) {
+            return Ok(Some(value));
+        }
+        let Some(base) = name.strip_suffix(['\'', '′']) else {
+            return Ok(None);
+        };
+        match self.resolve(base)? {
+            Some(function) => {
+                derivative::derive_function(base, function, None, &self.scope).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) fn run(&mut self, ast: Node) -> RuntimeError {
         self.visit(ast)
     }
 
     fn visit(&mut self, node: Node) -> RuntimeError {
+        // Recursive functions go as deep as the script makes them, so the stack is grown as
+        // needed instead of relying on the host's being big enough
+        stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || self.evaluate(node))
+    }
+
+    fn evaluate(&mut self, node: Node) -> RuntimeError {
         match node.ty {
+            NodeType::Number(x) if !x.contains(['.', 'e']) => match x.parse::<BigInt>() {
+                Ok(n) => Ok(Value::Integer(n)),
+                Err(reason) => self.error(
+                    format!("cannot parse '{}' as an integer", x),
+                    reason,
+                    node.range,
+                ),
+            },
             NodeType::Number(x) => match x.parse::<f64>() {
                 Ok(x) => Ok(Value::Number(x)),
                 Err(e) => self.error(
@@ -37,444 +126,281 @@ impl Interpreter<'_> {
                     node.range,
                 ),
             },
-            NodeType::Identifier(name) => Ok(self.scope.get(&name)),
+            NodeType::Bool(value) => Ok(Value::Bool(value)),
+            NodeType::Identifier(name) => match self.resolve(&name) {
+                Ok(Some(value)) => Ok(value),
+                Ok(None) => self.undefined(&name, node.range),
+                Err(error) => self.derivative_error(error, node.range),
+            },
             NodeType::Assignment(name, node) => {
                 let value = self.visit(*node)?;
                 self.scope.set(name, value.clone());
                 Ok(value)
             }
-            NodeType::Unary(op, node) => {
-                let value = self.visit(*node)?;
+            NodeType::Unary(op, operand) => match self.visit(*operand)? {
+                Value::Function(Function {
+                    name,
+                    arg_names,
+                    body,
+                    scope,
+                }) => Ok(Value::Function(Function {
+                    name,
+                    arg_names,
+                    scope,
+                    body: Box::new(Node {
+                        ty: match op {
+                            UnaryOp::Neg => NodeType::Binary(
+                                body,
+                                BinaryOp::Mul,
+                                Box::new(Node {
+                                    ty: NodeType::Number("-1.0".into()),
+                                    range: 0..0,
+                                }),
+                            ),
+                            _ => NodeType::Unary(op, body),
+                        },
+                        range: 0..0,
+                    }),
+                })),
+                value => match arithmetic::unary(op, value) {
+                    Ok(value) => Ok(value),
+                    Err(reason) => self.error("invalid operand".to_string(), reason, node.range),
+                },
+            },
+            NodeType::Binary(left, op @ (BinaryOp::And | BinaryOp::Or), right) => {
+                let l_value = self.visit(*left.clone())?;
+                let Value::Bool(a) = l_value else {
+                    return self.error(
+                        "invalid operand".to_string(),
+                        format!("expected a boolean, found {}", l_value),
+                        left.range,
+                    );
+                };
 
-                match op {
-                    UnaryOp::Pos => Ok(value),
-                    UnaryOp::Neg => match value {
-                        Value::Number(x) => Ok(Value::Number(-x)),
-                        Value::Function {
-                            name,
-                            arg_names,
-                            body,
-                        } => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Binary(
-                                    body,
-                                    BinaryOp::Mul,
-                                    Box::new(Node {
-                                        ty: NodeType::Number("-1.0".into()),
-                                        range: 0..0,
-                                    }),
-                                ),
-                                range: 0..0,
-                            }),
-                        }),
-                        _ => unimplemented!(),
-                    },
-                    UnaryOp::Sqrt => match value {
-                        Value::Number(x) => Ok(Value::Number(x.sqrt())),
-                        Value::Function {
-                            name,
-                            arg_names,
-                            body,
-                        } => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Unary(UnaryOp::Sqrt, body),
-                                range: 0..0,
-                            }),
-                        }),
-                        _ => unimplemented!(),
-                    },
-                    UnaryOp::Cbrt => match value {
-                        Value::Number(x) => Ok(Value::Number(x.cbrt())),
-                        Value::Function {
-                            name,
-                            arg_names,
-                            body,
-                        } => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Unary(UnaryOp::Cbrt, body),
-                                range: 0..0,
-                            }),
-                        }),
-                        _ => unimplemented!(),
-                    },
-                    UnaryOp::Fort => match value {
-                        Value::Number(x) => Ok(Value::Number(x.powf(0.25))),
-                        Value::Function {
-                            name,
-                            arg_names,
-                            body,
-                        } => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Unary(UnaryOp::Fort, body),
-                                range: 0..0,
-                            }),
-                        }),
-                        _ => unimplemented!(),
-                    },
-                    UnaryOp::Degree => match value {
-                        Value::Number(x) => Ok(Value::Number(x.to_radians())),
-                        Value::Function {
-                            name,
-                            arg_names,
-                            body,
-                        } => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Unary(UnaryOp::Degree, body),
-                                range: 0..0,
-                            }),
-                        }),
-                        _ => unimplemented!(),
-                    },
-                    UnaryOp::Fact => match value {
-                        Value::Number(x) => Ok(Value::Number({
-                            let mut product = 0.0;
-                            for n in 1..(x as i32) {
-                                product *= n as f64;
-                            }
-                            product
-                        })),
-                        Value::Function {
-                            name,
-                            arg_names,
-                            body,
-                        } => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Unary(UnaryOp::Fact, body),
-                                range: 0..0,
-                            }),
-                        }),
-                        _ => unimplemented!(),
-                    },
-                    UnaryOp::Abs => match value {
-                        Value::Number(x) => Ok(Value::Number(x.abs())),
-                        Value::Function {
-                            name,
-                            arg_names,
-                            body,
-                        } => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Unary(UnaryOp::Abs, body),
-                                range: 0..0,
-                            }),
-                        }),
-                        _ => unimplemented!(),
-                    },
-                    UnaryOp::Floor => match value {
-                        Value::Number(x) => Ok(Value::Number(x.floor())),
-                        Value::Function {
-                            name,
-                            arg_names,
-                            body,
-                        } => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Unary(UnaryOp::Floor, body),
-                                range: 0..0,
-                            }),
-                        }),
-                        _ => unimplemented!(),
-                    },
-                    UnaryOp::Ceil => match value {
-                        Value::Number(x) => Ok(Value::Number(x.ceil())),
-                        Value::Function {
-                            name,
-                            arg_names,
-                            body,
-                        } => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Unary(UnaryOp::Ceil, body),
-                                range: 0..0,
-                            }),
-                        }),
-                        _ => unimplemented!(),
-                    },
-                    UnaryOp::Round => match value {
-                        Value::Number(x) => Ok(Value::Number(x.round())),
-                        Value::Function {
-                            name,
-                            arg_names,
-                            body,
-                        } => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Unary(UnaryOp::Round, body),
-                                range: 0..0,
-                            }),
-                        }),
-                        _ => unimplemented!(),
-                    },
+                // Short-circuit so the right side is only evaluated when needed
+                if (op == BinaryOp::And && !a) || (op == BinaryOp::Or && a) {
+                    return Ok(Value::Bool(a));
+                }
+
+                match self.visit(*right.clone())? {
+                    Value::Bool(b) => Ok(Value::Bool(b)),
+                    r_value => self.error(
+                        "invalid operand".to_string(),
+                        format!("expected a boolean, found {}", r_value),
+                        right.range,
+                    ),
                 }
             }
             NodeType::Binary(left, op, right) => {
                 let l_value = self.visit(*left.clone())?;
                 let r_value = self.visit(*right.clone())?;
 
-                match op {
-                    BinaryOp::Add => match (l_value, r_value) {
-                        (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a + b)),
-                        (
-                            _,
-                            Value::Function {
-                                name,
-                                arg_names,
-                                body,
-                            },
-                        ) => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Binary(left, BinaryOp::Add, body),
-                                range: 0..0,
-                            }),
-                        }),
-                        (
-                            Value::Function {
-                                name,
-                                arg_names,
-                                body,
-                            },
-                            _,
-                        ) => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Binary(body, BinaryOp::Add, right),
-                                range: 0..0,
-                            }),
-                        }),
-                        _ => unimplemented!(),
-                    },
-                    BinaryOp::Sub => match (l_value, r_value) {
-                        (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a - b)),
-                        (
-                            _,
-                            Value::Function {
-                                name,
-                                arg_names,
-                                body,
-                            },
-                        ) => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Binary(left, BinaryOp::Sub, body),
-                                range: 0..0,
-                            }),
-                        }),
-                        (
-                            Value::Function {
-                                name,
-                                arg_names,
-                                body,
-                            },
-                            _,
-                        ) => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Binary(body, BinaryOp::Sub, right),
-                                range: 0..0,
-                            }),
-                        }),
-                        _ => unimplemented!(),
-                    },
-                    BinaryOp::Mul => match (l_value, r_value) {
-                        (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a * b)),
-                        (
-                            _,
-                            Value::Function {
-                                name,
-                                arg_names,
-                                body,
-                            },
-                        ) => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Binary(left, BinaryOp::Mul, body),
-                                range: 0..0,
-                            }),
-                        }),
-                        (
-                            Value::Function {
-                                name,
-                                arg_names,
-                                body,
-                            },
-                            _,
-                        ) => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Binary(body, BinaryOp::Mul, right),
-                                range: 0..0,
-                            }),
-                        }),
-                        _ => unimplemented!(),
-                    },
-                    BinaryOp::Div => match (l_value, r_value) {
-                        (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a / b)),
-                        (
-                            _,
-                            Value::Function {
-                                name,
-                                arg_names,
-                                body,
-                            },
-                        ) => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Binary(left, BinaryOp::Div, body),
-                                range: 0..0,
-                            }),
-                        }),
-                        (
-                            Value::Function {
-                                name,
-                                arg_names,
-                                body,
-                            },
-                            _,
-                        ) => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Binary(body, BinaryOp::Div, right),
-                                range: 0..0,
-                            }),
-                        }),
-                        _ => unimplemented!(),
-                    },
-                    BinaryOp::Mod => match (l_value, r_value) {
-                        (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a % b)),
-                        (
-                            _,
-                            Value::Function {
-                                name,
-                                arg_names,
-                                body,
-                            },
-                        ) => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Binary(left, BinaryOp::Mod, body),
-                                range: 0..0,
-                            }),
-                        }),
-                        (
-                            Value::Function {
-                                name,
-                                arg_names,
-                                body,
-                            },
-                            _,
-                        ) => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Binary(body, BinaryOp::Mod, right),
-                                range: 0..0,
-                            }),
-                        }),
-                        _ => unimplemented!(),
-                    },
-                    BinaryOp::Pow => match (l_value, r_value) {
-                        (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a.powf(b))),
-                        (
-                            _,
-                            Value::Function {
-                                name,
-                                arg_names,
-                                body,
-                            },
-                        ) => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Binary(left, BinaryOp::Pow, body),
-                                range: 0..0,
-                            }),
-                        }),
-                        (
-                            Value::Function {
-                                name,
-                                arg_names,
-                                body,
-                            },
-                            _,
-                        ) => Ok(Value::Function {
-                            name,
-                            arg_names,
-                            body: Box::new(Node {
-                                ty: NodeType::Binary(body, BinaryOp::Pow, right),
-                                range: 0..0,
-                            }),
-                        }),
-                        _ => unimplemented!(),
+                match (l_value, r_value) {
+                    (l_value, r_value) if op == BinaryOp::Compose => {
+                        match self.compose(l_value, r_value) {
+                            Ok(value) => Ok(value),
+                            Err(reason) => {
+                                self.error("invalid operands".to_string(), reason, node.range)
+                            }
+                        }
+                    }
+                    // Functions combine into a function, like f + g or 2f
+                    (l_value @ Value::Function(_), r_value)
+                    | (l_value, r_value @ Value::Function(_)) => {
+                        match self.combine(l_value, op, r_value) {
+                            Ok(value) => Ok(value),
+                            Err(reason) => {
+                                self.error("invalid operands".to_string(), reason, node.range)
+                            }
+                        }
+                    }
+                    (l_value, r_value) => match arithmetic::binary(op, l_value, r_value) {
+                        Ok(value) => Ok(value),
+                        Err(reason) => {
+                            // Point at the operator when there's a gap to point at
+                            let range = if left.range.end < right.range.start {
+                                left.range.end..right.range.start
+                            } else {
+                                node.range
+                            };
+                            self.error("invalid operands".to_string(), reason, range)
+                        }
                     },
                 }
             }
             NodeType::FnDef(name, arg_names, node) => {
-                let function = Value::Function {
+                let function = Value::Function(Function {
                     name: name.clone(),
                     arg_names,
                     body: node,
-                };
+                    scope: Captured::new(&self.scope),
+                });
                 self.scope.set(name, function.clone());
                 Ok(function)
             }
+            NodeType::Lambda(arg_names, body) => Ok(Value::Function(Function {
+                name: "".into(),
+                arg_names,
+                body,
+                scope: Captured::new(&self.scope),
+            })),
             NodeType::Call(name, args) => {
-                let mut arg_values: Vec<Value> = vec![];
-                for arg in args {
-                    let value = self.visit(arg)?;
-                    arg_values.push(value);
+                let (arg_values, arg_ranges) = self.arguments(args)?;
+                let name_range = node.range.start..node.range.start + name.len();
+                let function = match self.resolve(&name) {
+                    Ok(Some(function)) => function,
+                    Ok(None) => return self.undefined(&name, name_range),
+                    Err(error) => return self.derivative_error(error, name_range),
+                };
+                self.call_value(&name, function, arg_values, &arg_ranges, node.range)
+            }
+            // Anything else that's called, like (f ∘ g)(x) or adder(2)(3)
+            NodeType::Apply(callee, args) => {
+                let name = format!("{:#}", callee);
+                let function = self.visit(*callee)?;
+                let (arg_values, arg_ranges) = self.arguments(args)?;
+                self.call_value(&name, function, arg_values, &arg_ranges, node.range)
+            }
+            NodeType::Vector(nodes) => {
+                let mut elements: Vec<Value> = vec![];
+                for node in nodes {
+                    elements.push(self.visit(node)?);
                 }
-
-                let function = self.scope.get(&name);
-                match function {
-                    Value::Function {
-                        name: _,
-                        arg_names,
-                        body,
-                    } => {
-                        let mut interpreter = Interpreter::default();
-                        interpreter.scope.parent = Some(&self.scope);
-                        for (name, value) in arg_names.iter().zip(arg_values) {
-                            interpreter.scope.set(name.clone(), value.clone());
+                Ok(Value::Vector(elements))
+            }
+            NodeType::Matrix(rows) => {
+                let (height, width) = (rows.len(), rows[0].len());
+                let mut data: Vec<f64> = vec![];
+                for node in rows.into_iter().flatten() {
+                    let range = node.range.clone();
+                    let value = self.visit(node)?;
+                    match value.as_f64() {
+                        Some(x) => data.push(x),
+                        None => {
+                            return self.error(
+                                "invalid matrix".to_string(),
+                                format!("matrix entries must be real numbers, found {}", value),
+                                range,
+                            )
                         }
-                        interpreter.visit(*body)
                     }
-                    Value::NativeFunction(function) => match function(&arg_values) {
-                        Ok(value) => Ok(value),
-                        Err(reason) => self.error("".to_string(), reason, node.range),
+                }
+                Ok(Value::Matrix(Matrix::new(height, width, data).unwrap()))
+            }
+            NodeType::Index(node, indices) => {
+                let mut value = self.visit(*node)?;
+                for index in indices {
+                    let range = index.range.clone();
+                    let index = self.visit(index)?;
+                    // Indexing a matrix gives a row, which can be indexed again for an entry
+                    let mut elements = match value {
+                        Value::Vector(elements) => elements,
+                        Value::Matrix(matrix) => (0..matrix.rows())
+                            .map(|i| {
+                                Value::Vector(
+                                    matrix.row(i).into_iter().map(Value::Number).collect(),
+                                )
+                            })
+                            .collect(),
+                        _ => {
+                            return self.error(
+                                "invalid index".to_string(),
+                                format!("{} cannot be indexed", value),
+                                range,
+                            )
+                        }
+                    };
+
+                    // Indices start at 1, like subscripts in math
+                    let position = match &index {
+                        Value::Integer(n) => n.to_u32().map(|n| n as usize),
+                        _ => None,
+                    };
+                    value = match position {
+                        Some(i) if (1..=elements.len()).contains(&i) => elements.swap_remove(i - 1),
+                        _ => {
+                            return self.error(
+                                "invalid index".to_string(),
+                                format!(
+                                    "expected an integer from 1 to {}, found {}",
+                                    elements.len(),
+                                    index
+                                ),
+                                range,
+                            )
+                        }
+                    };
+                }
+                Ok(value)
+            }
+            NodeType::If(condition, then, otherwise) => {
+                let range = condition.range.clone();
+                match self.visit(*condition)? {
+                    Value::Bool(true) => self.visit(*then),
+                    Value::Bool(false) => match otherwise {
+                        Some(otherwise) => self.visit(*otherwise),
+                        None => self.error(
+                            "no matching case".to_string(),
+                            "none of the conditions were true".to_string(),
+                            node.range,
+                        ),
                     },
-                    _ => self.error(
-                        format!("{} is not a function", name),
-                        "".to_string(),
-                        node.range,
+                    value => self.error(
+                        "invalid condition".to_string(),
+                        format!("expected a boolean, found {}", value),
+                        range,
                     ),
                 }
             }
+            NodeType::Sum(index, lower, upper, body) => {
+                self.series(BinaryOp::Add, index, *lower, *upper, *body, node.range)
+            }
+            NodeType::Product(index, lower, upper, body) => {
+                self.series(BinaryOp::Mul, index, *lower, *upper, *body, node.range)
+            }
+            NodeType::Integral(var, lower, upper, body) => {
+                self.integral(var, *lower, *upper, *body, node.range)
+            }
+            NodeType::Derivative(operand, var) => {
+                // A named function keeps its arguments, anything else becomes a function of var
+                if let NodeType::Identifier(name) = &operand.ty {
+                    if let Ok(Some(function)) = self.resolve(name) {
+                        if matches!(function, Value::Function(_) | Value::NativeFunction(_)) {
+                            return match derivative::derive_function(
+                                name,
+                                function,
+                                Some(&var),
+                                &self.scope,
+                            ) {
+                                Ok(value) => Ok(value),
+                                Err(error) => self.derivative_error(error, node.range),
+                            };
+                        }
+                    }
+                }
+
+                match derivative::derive(&operand, &var, &[], &self.scope) {
+                    Ok(body) => Ok(Value::Function(Function {
+                        name: "".into(),
+                        arg_names: vec![var],
+                        body: Box::new(body),
+                        scope: Captured::new(&self.scope),
+                    })),
+                    Err(error) => self.derivative_error(error, node.range),
+                }
+            }
+            NodeType::Solve(equation, var) => self.solve(*equation, var, node.range),
+            NodeType::Block(nodes) => {
+                let mut interpreter = self.child(Scope::child(&self.scope));
+                let mut result = Ok(Value::Number(0.0));
+                for node in nodes {
+                    result = interpreter.visit(node);
+                    if result.is_err() {
+                        break;
+                    }
+                }
+                result
+            }
             NodeType::Statements(nodes) => {
                 let mut rtn_value = Value::Number(0.0);
                 for node in nodes {
@@ -486,6 +412,497 @@ impl Interpreter<'_> {
         }
     }
 
+    /// Evaluates a sum or product with `index` bound in a child scope. An infinite upper
+    /// bound keeps going until the terms stop changing the result.
+    fn series(
+        &mut self,
+        op: BinaryOp,
+        index: Rc<str>,
+        lower: Node,
+        upper: Node,
+        body: Node,
+        range: Range<usize>,
+    ) -> RuntimeError {
+        let mut bounds: Vec<Option<BigInt>> = vec![];
+        for bound in [lower, upper] {
+            let bound_range = bound.range.clone();
+            let value = self.visit(bound)?;
+            bounds.push(match value {
+                Value::Integer(n) => Some(n),
+                Value::Number(x) if x == f64::INFINITY && bounds.len() == 1 => None,
+                Value::Number(x) if x.fract() == 0.0 => BigInt::from_f64(x),
+                _ => {
+                    return self.error(
+                        "invalid bound".to_string(),
+                        format!("expected an integer, found {}", value),
+                        bound_range,
+                    )
+                }
+            });
+        }
+        // Only the upper bound can be infinite
+        let upper = bounds.pop().unwrap();
+        let mut k = bounds.pop().unwrap().unwrap();
+
+        let mut interpreter = self.child(Scope::child(&self.scope));
+        let Some(upper) = upper else {
+            return interpreter.infinite_series(op, index, k, body, range);
+        };
+        let mut result = match op {
+            BinaryOp::Add => Value::from(0),
+            _ => Value::from(1),
+        };
+        while k <= upper {
+            interpreter
+                .scope
+                .set(index.clone(), Value::Integer(k.clone()));
+            let term = interpreter.visit(body.clone())?;
+            result = match arithmetic::binary(op, result, term) {
+                Ok(value) => value,
+                Err(reason) => return self.error("invalid term".to_string(), reason, body.range),
+            };
+            k = &k + &BigInt::one();
+        }
+        Ok(result)
+    }
+
+    /// Sums or multiplies the terms from `k` on in floating point, since exact partial sums of
+    /// something like 1/k² grow without bound. Series that converge too slowly to settle, like
+    /// 1/k², are extrapolated from the partial results after 2¹⁰, 2¹¹, 2¹², … terms. Those
+    /// extrapolations get closer together with every doubling until they agree, so a series whose
+    /// extrapolations stop doing that is given up on as divergent.
+    fn infinite_series(
+        &mut self,
+        op: BinaryOp,
+        index: Rc<str>,
+        mut k: BigInt,
+        body: Node,
+        range: Range<usize>,
+    ) -> RuntimeError {
+        let mut result = match op {
+            BinaryOp::Add => 0.0,
+            _ => 1.0,
+        };
+        // Kahan summation, so a million terms don't lose digits to rounding
+        let mut compensation = 0.0;
+        let mut partials = vec![];
+        let mut spread = None;
+        let mut settled = 0;
+        let mut terms = 0;
+        while terms < MAX_SERIES_TERMS {
+            terms += 1;
+            self.scope.set(index.clone(), Value::Integer(k.clone()));
+            let value = self.visit(body.clone())?;
+            let Some(term) = value.as_f64() else {
+                return self.error(
+                    "invalid term".to_string(),
+                    format!(
+                        "the terms of an infinite series must be real numbers, found {}",
+                        value
+                    ),
+                    body.range,
+                );
+            };
+            k = &k + &BigInt::one();
+
+            let previous = result;
+            if op == BinaryOp::Add {
+                let y = term - compensation;
+                result = previous + y;
+                compensation = (result - previous) - y;
+            } else {
+                result *= term;
+            }
+            if !result.is_finite() {
+                return Ok(Value::Number(result));
+            }
+            // A few small terms in a row, so a single zero term doesn't stop it early
+            settled = if (result - previous).abs() <= SERIES_TOLERANCE * result.abs() {
+                settled + 1
+            } else {
+                0
+            };
+            if settled == 10 {
+                return Ok(Value::Number(result));
+            }
+
+            if terms >= 1 << 10 && terms.is_power_of_two() {
+                partials.push(result);
+                // The deepest extrapolation whose last two estimates agree is the result
+                let mut estimates = partials.clone();
+                let mut limit = None;
+                let previous_spread = spread.take();
+                while estimates.len() >= 3 {
+                    estimates = aitken(&estimates);
+                    if let &[.., last, current] = estimates.as_slice() {
+                        let difference = (current - last).abs();
+                        if difference <= EXTRAPOLATION_TOLERANCE * current.abs() {
+                            limit = Some(current);
+                        }
+                        spread = Some(spread.map_or(difference, |s: f64| s.min(difference)));
+                    }
+                }
+                if let Some(limit) = limit {
+                    return Ok(Value::Number(limit));
+                }
+                let converging = matches!((spread, previous_spread), (Some(s), Some(p)) if s < p);
+                if partials.len() > 4 && !converging {
+                    break;
+                }
+            }
+        }
+        let reason = match spread {
+            Some(spread) => format!(
+                "the estimates still differ by {:e} after {} terms",
+                spread, terms
+            ),
+            None => format!("still changing after {} terms", terms),
+        };
+        self.error("series does not converge".to_string(), reason, range)
+    }
+
+    /// Numerically integrates `body` over `var` with adaptive Gauss–Kronrod quadrature
+    fn integral(
+        &mut self,
+        var: Rc<str>,
+        lower: Node,
+        upper: Node,
+        body: Node,
+        range: Range<usize>,
+    ) -> RuntimeError {
+        let mut bounds: Vec<f64> = vec![];
+        for bound in [lower, upper] {
+            let bound_range = bound.range.clone();
+            let value = self.visit(bound)?;
+            match value.as_f64() {
+                Some(x) if !x.is_nan() => bounds.push(x),
+                _ => {
+                    return self.error(
+                        "invalid bound".to_string(),
+                        format!("expected a real number, found {}", value),
+                        bound_range,
+                    )
+                }
+            }
+        }
+
+        let mut interpreter = self.child(Scope::child(&self.scope));
+        let integrand = |x: f64| -> Result<f64, AmiError> {
+            interpreter.scope.set(var.clone(), Value::Number(x));
+            let value = interpreter.visit(body.clone())?;
+            match value.as_f64() {
+                Some(y) if y.is_finite() => Ok(y),
+                Some(_) => interpreter.error(
+                    "invalid integrand".to_string(),
+                    format!("the integrand is not finite at {} = {}", var, x),
+                    body.range.clone(),
+                ),
+                None => interpreter.error(
+                    "invalid integrand".to_string(),
+                    format!("expected a real number, found {}", value),
+                    body.range.clone(),
+                ),
+            }
+        };
+        let (result, error) = math::integrate(integrand, bounds[0], bounds[1])?;
+
+        self.report(|| Diagnostic::IntegralError(error));
+        // Divergent integrals keep growing as they're subdivided, so the error stays large
+        if !result.is_finite() || error > INTEGRAL_CONVERGENCE * result.abs().max(1.0) {
+            let reason = if error.is_finite() {
+                format!("the error estimate is still {:.1e}", error)
+            } else {
+                "it grows without bound".to_string()
+            };
+            return self.error("integral does not converge".to_string(), reason, range);
+        }
+        Ok(Value::Number(result))
+    }
+
+    /// Evaluates the arguments of a call, along with where each one is
+    fn arguments(&mut self, args: Vec<Node>) -> Result<(Vec<Value>, Vec<Range<usize>>), AmiError> {
+        let arg_ranges = args.iter().map(|arg| arg.range.clone()).collect();
+        let mut arg_values: Vec<Value> = vec![];
+        for arg in args {
+            arg_values.push(self.visit(arg)?);
+        }
+        Ok((arg_values, arg_ranges))
+    }
+
+    /// Calls the value of the callee of a call, which is written as `name`
+    fn call_value(
+        &mut self,
+        name: &str,
+        function: Value,
+        arg_values: Vec<Value>,
+        arg_ranges: &[Range<usize>],
+        range: Range<usize>,
+    ) -> RuntimeError {
+        match function {
+            Value::Function(Function { ref arg_names, .. })
+                if arg_names.len() != arg_values.len() =>
+            {
+                self.error(
+                    format!("invalid call to {}", name),
+                    arity_error(name, arg_names.len(), arg_values.len()),
+                    range,
+                )
+            }
+            Value::Function(Function {
+                arg_names,
+                body,
+                scope,
+                ..
+            }) => self.call_function(&arg_names, *body, &scope, arg_values, range),
+            Value::NativeFunction(function) => {
+                let args = Args::new(&arg_values, arg_ranges, range.clone());
+                function
+                    .call(self, &args)
+                    .map_err(|error| error.into_error(&function.name, range))
+            }
+            value => self.error(
+                format!("{} is not a function", name),
+                format!("expected a function, found {}", value),
+                range,
+            ),
+        }
+    }
+
+    /// Runs the body of a user function in a new scope inside the one it was defined in, so
+    /// it sees the variables around its definition rather than the caller's
+    fn call_function(
+        &self,
+        arg_names: &[Rc<str>],
+        body: Node,
+        scope: &Captured,
+        args: Vec<Value>,
+        range: Range<usize>,
+    ) -> RuntimeError {
+        if self.depth == MAX_CALL_DEPTH {
+            return self.error(
+                "maximum recursion depth exceeded".to_string(),
+                format!("functions can only call each other {} deep", MAX_CALL_DEPTH),
+                range,
+            );
+        }
+        let mut interpreter = self.child(Scope::child(&scope.scope()));
+        interpreter.depth += 1;
+        for (name, value) in arg_names.iter().zip(args) {
+            interpreter.scope.set(name.clone(), value);
+        }
+        interpreter.visit(body)
+    }
+
+    /// Calls a function value with arguments that are already evaluated, for built-ins that
+    /// take functions. Errors about the arguments point at `range`.
+    pub fn call(
+        &mut self,
+        function: &Value,
+        args: &[Value],
+        range: Range<usize>,
+    ) -> Result<Value, NativeError> {
+        match function {
+            Value::Function(Function {
+                name,
+                arg_names,
+                body,
+                scope,
+            }) => {
+                if arg_names.len() != args.len() {
+                    let name = match &**name {
+                        "" => function.to_string(),
+                        name => name.to_string(),
+                    };
+                    return Err(NativeError::Argument(
+                        range,
+                        arity_error(&name, arg_names.len(), args.len()),
+                    ));
+                }
+                Ok(self.call_function(arg_names, *body.clone(), scope, args.to_vec(), range)?)
+            }
+            Value::NativeFunction(function) => function
+                .call(self, &Args::new(args, &[], range.clone()))
+                .map_err(|error| NativeError::Runtime(error.into_error(&function.name, range))),
+            value => Err(NativeError::Argument(
+                range,
+                format!("{} is not a function", value),
+            )),
+        }
+    }
+
+    /// `f ∘ g`, the function that applies `g` and then `f`
+    fn compose(&self, f: Value, g: Value) -> Result<Value, String> {
+        let name = |function: &Value, fallback: &str| -> Result<Rc<str>, String> {
+            match function {
+                Value::Function(Function { name, .. }) if !name.is_empty() => Ok(name.clone()),
+                Value::Function(_) => Ok(fallback.into()),
+                Value::NativeFunction(function) => Ok(function.name.clone()),
+                value => Err(format!("{} is not a function", value)),
+            }
+        };
+        let (outer, inner) = (name(&f, "f")?, name(&g, "g")?);
+        let arg_names = match &g {
+            Value::Function(Function { arg_names, .. }) => arg_names.clone(),
+            _ => vec!["x".into()],
+        };
+
+        // The body calls both functions by name, from a scope where those names are bound
+        let scope = Scope::child(&self.scope);
+        scope.set(outer.clone(), f);
+        scope.set(inner.clone(), g);
+        let args = arg_names
+            .iter()
+            .map(|arg| Node::from(NodeType::Identifier(arg.clone())))
+            .collect();
+        let body = NodeType::Call(outer, vec![Node::from(NodeType::Call(inner, args))]);
+        Ok(Value::Function(Function {
+            name: "".into(),
+            arg_names,
+            body: Box::new(Node::from(body)),
+            scope: Captured::new(&scope),
+        }))
+    }
+
+    /// `left op right` where at least one side is a function, as the function that applies
+    /// `op` to their results, like `f + g`. Like in `compose`, the body calls the functions by
+    /// name from a scope where those names are bound, so each runs in the scope it was defined
+    /// in, and the functions are called with the same arguments.
+    fn combine(&self, left: Value, op: BinaryOp, right: Value) -> Result<Value, String> {
+        let arg_names = match (&left, &right) {
+            (Value::Function(f), Value::Function(g)) if f.arg_names.len() != g.arg_names.len() => {
+                let name = |function: &Function| match &*function.name {
+                    "" => function.to_string(),
+                    name => name.to_string(),
+                };
+                return Err(format!(
+                    "{} takes {}, but {} takes {}",
+                    name(f),
+                    plural(f.arg_names.len(), "argument"),
+                    name(g),
+                    plural(g.arg_names.len(), "argument")
+                ));
+            }
+            (Value::Function(f), _) | (_, Value::Function(f)) => f.arg_names.clone(),
+            _ => unreachable!("one side is a function"),
+        };
+
+        let scope = Scope::child(&self.scope);
+        let mut taken = arg_names.clone();
+        let mut operand = |value: Value, fallback: &str| -> Node {
+            let name = match &value {
+                // Integers are written into the body, so 2f prints as 2f(x)
+                Value::Integer(n) => return Node::from(NodeType::Number(n.to_string().into())),
+                Value::Function(function) if !function.name.is_empty() => &function.name,
+                _ => fallback,
+            };
+            // Numbered, so neither side hides the other or an argument
+            let mut unique: Rc<str> = name.into();
+            for n in 2.. {
+                if !taken.contains(&unique) {
+                    break;
+                }
+                unique = format!("{}{}", name, n).into();
+            }
+            taken.push(unique.clone());
+
+            let node = match value {
+                Value::Function(_) => NodeType::Call(
+                    unique.clone(),
+                    arg_names
+                        .iter()
+                        .map(|arg| Node::from(NodeType::Identifier(arg.clone())))
+                        .collect(),
+                ),
+                _ => NodeType::Identifier(unique.clone()),
+            };
+            scope.set(unique, value);
+            Node::from(node)
+        };
+        let body = NodeType::Binary(
+            Box::new(operand(left, "f")),
+            op,
+            Box::new(operand(right, "g")),
+        );
+
+        Ok(Value::Function(Function {
+            name: "".into(),
+            arg_names: arg_names.clone(),
+            body: Box::new(Node::from(body)),
+            scope: Captured::new(&scope),
+        }))
+    }
+
+    /// Solves `equation` for `var`. Polynomials have all their roots found, complex ones
+    /// included, while anything else only has its real roots between ±[`solver::SCAN_RANGE`]
+    /// found numerically.
+    fn solve(&mut self, equation: Node, var: Rc<str>, range: Range<usize>) -> RuntimeError {
+        // f = g is solved as f - g = 0
+        let expression = match equation.ty {
+            NodeType::Binary(left, BinaryOp::Eq, right) => {
+                Node::from(NodeType::Binary(left, BinaryOp::Sub, right))
+            }
+            _ => equation,
+        };
+
+        let mut interpreter = self.child(Scope::child(&self.scope));
+
+        let inlined = solver::inline(&expression, &self.scope, 0);
+        let coefficients = solver::polynomial(&inlined, &var, &mut |node| {
+            interpreter.visit(node.clone()).ok()?.as_f64()
+        });
+        if let Some(coefficients) = coefficients {
+            if let [constant] = coefficients.as_slice() {
+                let reason = if *constant == 0.0 {
+                    format!("every value of {} is a solution", var)
+                } else {
+                    "there are no solutions".to_string()
+                };
+                return self.error("invalid equation".to_string(), reason, range);
+            }
+            return Ok(Value::Vector(solver::polynomial_roots(&coefficients)));
+        }
+
+        // Values that aren't real, like ln of a negative number, just don't count as roots
+        let f = |x: f64| -> Result<f64, AmiError> {
+            interpreter.scope.set(var.clone(), Value::Number(x));
+            Ok(interpreter
+                .visit(expression.clone())?
+                .as_f64()
+                .unwrap_or(f64::NAN))
+        };
+        let roots = match solver::find_roots(f)? {
+            Roots::Points(roots) => roots,
+            Roots::Interval(lower, upper) => {
+                let reason = match (lower, upper) {
+                    (None, None) => format!("every value of {} is a solution", var),
+                    (Some(a), None) => {
+                        format!("every value of {} from {} up is a solution", var, a)
+                    }
+                    (None, Some(b)) => format!("every value of {} up to {} is a solution", var, b),
+                    (Some(a), Some(b)) => format!(
+                        "every value of {} between {} and {} is a solution",
+                        var, a, b
+                    ),
+                };
+                return self.error("invalid equation".to_string(), reason, range);
+            }
+        };
+        if roots.is_empty() {
+            return self.error(
+                "no solution found".to_string(),
+                format!(
+                    "couldn't find any real roots between {} and {}",
+                    -solver::SCAN_RANGE,
+                    solver::SCAN_RANGE
+                ),
+                range,
+            );
+        }
+        Ok(Value::Vector(
+            roots.into_iter().map(Value::Number).collect(),
+        ))
+    }
+
     fn add_builtins(&mut self) {
         macro_rules! add_var {
             ($name:literal, $value:expr) => {
@@ -499,202 +916,347 @@ impl Interpreter<'_> {
         add_var!("𝜑", (1.0 + 5.0_f64.sqrt()) / 2.0);
         add_var!("𝜙", (1.0 + 5.0_f64.sqrt()) / 2.0);
         add_var!("∞", f64::INFINITY);
+        // For keyboards without the symbols
+        add_var!("pi", std::f64::consts::PI);
+        add_var!("tau", std::f64::consts::TAU);
+        add_var!("phi", (1.0 + 5.0_f64.sqrt()) / 2.0);
+        add_var!("inf", f64::INFINITY);
+        add_var!("i", Complex::I);
 
         macro_rules! add_fn {
-            ($name:literal, $value:expr) => {
-                self.scope.set($name.into(), Value::NativeFunction($value));
+            ($name:literal, $arity:expr, $function:expr) => {
+                self.scope.set(
+                    $name.into(),
+                    Value::NativeFunction(NativeFunction::new($name, $arity, $function)),
+                );
             };
         }
 
-        add_fn!("abs", |args| {
-            match args.get(0) {
-                Some(Value::Number(value)) => Ok(Value::Number(value.abs())),
-                _ => Err("expected a number".to_string()),
-            }
+        add_fn!("abs", 1..=1, |_, args| match args.first() {
+            Some(value) => arithmetic::unary(UnaryOp::Abs, value.clone())
+                .map_err(|reason| args.error(0, reason)),
+            _ => Err(args.error(0, "expected a number".to_string())),
         });
-        add_fn!("floor", |args| {
-            match args.get(0) {
-                Some(Value::Number(value)) => Ok(Value::Number(value.floor())),
-                _ => Err("expected a number".to_string()),
+        add_fn!("floor", 1..=1, |_, args| match args.first() {
+            Some(value) => {
+                arithmetic::unary(UnaryOp::Floor, value.clone())
+                    .map_err(|reason| args.error(0, reason))
             }
+            _ => Err(args.error(0, "expected a number".to_string())),
         });
-        add_fn!("ceil", |args| {
-            match args.get(0) {
-                Some(Value::Number(value)) => Ok(Value::Number(value.ceil())),
-                _ => Err("expected a number".to_string()),
+        add_fn!("ceil", 1..=1, |_, args| match args.first() {
+            Some(value) => {
+                arithmetic::unary(UnaryOp::Ceil, value.clone())
+                    .map_err(|reason| args.error(0, reason))
             }
+            _ => Err(args.error(0, "expected a number".to_string())),
         });
-        add_fn!("round", |args| {
-            match args.get(0) {
-                Some(Value::Number(value)) => Ok(Value::Number(value.round())),
-                _ => Err("expected a number".to_string()),
+        add_fn!("round", 1..=1, |_, args| match args.first() {
+            Some(value) => {
+                arithmetic::unary(UnaryOp::Round, value.clone())
+                    .map_err(|reason| args.error(0, reason))
             }
+            _ => Err(args.error(0, "expected a number".to_string())),
         });
-        add_fn!("trunc", |args| {
-            match args.get(0) {
-                Some(Value::Number(value)) => Ok(Value::Number(value.trunc())),
-                _ => Err("expected a number".to_string()),
+
+        macro_rules! add_math_fn {
+            ($name:literal, $f:expr) => {
+                add_fn!(
+                    $name,
+                    1..=1,
+                    |_, args| match args.first().and_then(Value::as_f64) {
+                        Some(x) => Ok(Value::Number($f(x))),
+                        _ => Err(args.error(0, "expected a number".to_string())),
+                    }
+                );
+            };
+        }
+
+        add_fn!(
+            "float",
+            1..=1,
+            |_, args| match args.first().and_then(Value::as_f64) {
+                Some(x) => Ok(Value::Number(x)),
+                _ => Err(args.error(0, "expected a number".to_string())),
             }
+        );
+        add_math_fn!("trunc", f64::trunc);
+        add_math_fn!("fract", f64::fract);
+
+        // Uses the real function when the argument is in its real domain, and the complex one
+        // otherwise, so `sqrt(-1)` is `i` instead of NaN
+        macro_rules! add_complex_fn {
+            ($name:literal, $real:expr, $complex:expr, $domain:expr) => {
+                add_fn!($name, 1..=1, |_, args| match args.first() {
+                    Some(Value::Complex(z)) => Ok(arithmetic::from_complex($complex(*z))),
+                    Some(value) => match value.as_f64() {
+                        Some(x) if ($domain)(x) => Ok(Value::Number($real(x))),
+                        Some(x) => Ok(arithmetic::from_complex($complex(Complex::from(x)))),
+                        None => Err(args.error(0, "expected a number".to_string())),
+                    },
+                    None => Err(args.error(0, "expected a number".to_string())),
+                });
+            };
+        }
+
+        add_complex_fn!("sqrt", f64::sqrt, Complex::sqrt, |x: f64| x >= 0.0);
+        add_complex_fn!("cbrt", f64::cbrt, Complex::cbrt, |_| true);
+        add_complex_fn!("exp", f64::exp, Complex::exp, |_| true);
+        add_complex_fn!("ln", f64::ln, Complex::ln, |x: f64| x >= 0.0);
+
+        add_complex_fn!("sin", f64::sin, Complex::sin, |_| true);
+        add_complex_fn!("cos", f64::cos, Complex::cos, |_| true);
+        add_complex_fn!("tan", f64::tan, Complex::tan, |_| true);
+        add_complex_fn!("asin", f64::asin, Complex::asin, |x: f64| x.abs() <= 1.0);
+        add_complex_fn!("acos", f64::acos, Complex::acos, |x: f64| x.abs() <= 1.0);
+        add_complex_fn!("atan", f64::atan, Complex::atan, |_| true);
+        add_complex_fn!("sinh", f64::sinh, Complex::sinh, |_| true);
+        add_complex_fn!("cosh", f64::cosh, Complex::cosh, |_| true);
+        add_complex_fn!("tanh", f64::tanh, Complex::tanh, |_| true);
+        add_complex_fn!("asinh", f64::asinh, Complex::asinh, |_| true);
+        add_complex_fn!("acosh", f64::acosh, Complex::acosh, |x: f64| x >= 1.0);
+        add_complex_fn!("atanh", f64::atanh, Complex::atanh, |x: f64| x.abs() <= 1.0);
+
+        add_fn!("re", 1..=1, |_, args| match args.first() {
+            Some(Value::Complex(z)) => Ok(Value::Number(z.re)),
+            Some(value) if value.as_f64().is_some() => Ok(value.clone()),
+            _ => Err(args.error(0, "expected a number".to_string())),
         });
-        add_fn!("fract", |args| {
-            match args.get(0) {
-                Some(Value::Number(value)) => Ok(Value::Number(value.fract())),
-                _ => Err("expected a number".to_string()),
-            }
+        add_fn!("im", 1..=1, |_, args| match args.first() {
+            Some(Value::Complex(z)) => Ok(Value::Number(z.im)),
+            Some(value) if value.as_f64().is_some() => Ok(Value::from(0)),
+            _ => Err(args.error(0, "expected a number".to_string())),
         });
-        add_fn!("sqrt", |args| {
-            match args.get(0) {
-                Some(Value::Number(value)) => Ok(Value::Number(value.sqrt())),
-                _ => Err("expected a number".to_string()),
-            }
+        add_fn!("arg", 1..=1, |_, args| match args.first() {
+            Some(Value::Complex(z)) => Ok(Value::Number(z.arg())),
+            Some(value) => match value.as_f64() {
+                Some(x) => Ok(Value::Number(Complex::from(x).arg())),
+                None => Err(args.error(0, "expected a number".to_string())),
+            },
+            None => Err(args.error(0, "expected a number".to_string())),
         });
-        add_fn!("cbrt", |args| {
-            match args.get(0) {
-                Some(Value::Number(value)) => Ok(Value::Number(value.cbrt())),
-                _ => Err("expected a number".to_string()),
-            }
+        add_fn!("conj", 1..=1, |_, args| match args.first() {
+            Some(Value::Complex(z)) => Ok(Value::Complex(z.conj())),
+            Some(value) if value.as_f64().is_some() => Ok(value.clone()),
+            _ => Err(args.error(0, "expected a number".to_string())),
         });
-        add_fn!("ln", |args| {
-            match args.get(0) {
-                Some(Value::Number(value)) => Ok(Value::Number(value.ln())),
-                _ => Err("expected a number".to_string()),
-            }
+        add_fn!("polar", 2..=2, |_, args| {
+            let (r, theta) = (number_arg(args, 0)?, number_arg(args, 1)?);
+            Ok(arithmetic::from_complex(Complex::from_polar(r, theta)))
         });
 
-        add_fn!("sin", |args| {
-            match args.get(0) {
-                Some(Value::Number(value)) => Ok(Value::Number(value.sin())),
-                _ => Err("expected a number".to_string()),
-            }
+        add_math_fn!("gamma", math::gamma);
+        add_math_fn!("lgamma", math::lgamma);
+        add_math_fn!("digamma", math::digamma);
+        add_fn!("beta", 2..=2, |_, args| {
+            let (a, b) = (number_arg(args, 0)?, number_arg(args, 1)?);
+            Ok(Value::Number(math::beta(a, b)))
         });
-        add_fn!("cos", |args| {
-            match args.get(0) {
-                Some(Value::Number(value)) => Ok(Value::Number(value.cos())),
-                _ => Err("expected a number".to_string()),
+
+        add_fn!("map", 2..=2, |interpreter, args| {
+            let f = function_arg(args, 0)?;
+            let mut elements: Vec<Value> = vec![];
+            for element in vector_arg(args, 1)? {
+                elements.push(interpreter.call(f, slice::from_ref(element), args.range(1))?);
             }
+            Ok(Value::Vector(elements))
         });
-        add_fn!("tan", |args| {
-            match args.get(0) {
-                Some(Value::Number(value)) => Ok(Value::Number(value.tan())),
-                _ => Err("expected a number".to_string()),
+        add_fn!("filter", 2..=2, |interpreter, args| {
+            let f = function_arg(args, 0)?;
+            let mut elements: Vec<Value> = vec![];
+            for element in vector_arg(args, 1)? {
+                match interpreter.call(f, slice::from_ref(element), args.range(1))? {
+                    Value::Bool(true) => elements.push(element.clone()),
+                    Value::Bool(false) => {}
+                    value => {
+                        return Err(args.error(0, format!("expected a boolean, found {}", value)))
+                    }
+                }
             }
+            Ok(Value::Vector(elements))
         });
-        add_fn!("asin", |args| {
-            match args.get(0) {
-                Some(Value::Number(value)) => Ok(Value::Number(value.asin())),
-                _ => Err("expected a number".to_string()),
+        add_fn!("fold", 3..=3, |interpreter, args| {
+            let f = function_arg(args, 0)?;
+            let mut accumulator = args[1].clone();
+            for element in vector_arg(args, 2)? {
+                accumulator =
+                    interpreter.call(f, &[accumulator, element.clone()], args.range(2))?;
             }
+            Ok(accumulator)
         });
-        add_fn!("acos", |args| {
-            match args.get(0) {
-                Some(Value::Number(value)) => Ok(Value::Number(value.acos())),
-                _ => Err("expected a number".to_string()),
-            }
+        add_fn!("compose", 2..=2, |interpreter, args| {
+            let f = function_arg(args, 0)?;
+            let g = function_arg(args, 1)?;
+            Ok(interpreter.compose(f.clone(), g.clone())?)
         });
-        add_fn!("atan", |args| {
-            match args.get(0) {
-                Some(Value::Number(value)) => Ok(Value::Number(value.atan())),
-                _ => Err("expected a number".to_string()),
-            }
+        add_fn!("apply", 2..=2, |interpreter, args| {
+            let f = function_arg(args, 0)?;
+            interpreter.call(f, vector_arg(args, 1)?, args.range(1))
         });
-        add_fn!("sinh", |args| {
-            match args.get(0) {
-                Some(Value::Number(value)) => Ok(Value::Number(value.sinh())),
-                _ => Err("expected a number".to_string()),
-            }
+
+        add_fn!("simplify", 1..=1, |_, args| match &args[0] {
+            Value::Function(Function {
+                name,
+                arg_names,
+                body,
+                scope,
+            }) => Ok(Value::Function(Function {
+                name: name.clone(),
+                arg_names: arg_names.clone(),
+                body: Box::new(simplify::simplify(body)),
+                scope: scope.clone(),
+            })),
+            value => Ok(value.clone()),
         });
-        add_fn!("cosh", |args| {
-            match args.get(0) {
-                Some(Value::Number(value)) => Ok(Value::Number(value.cosh())),
-                _ => Err("expected a number".to_string()),
-            }
+
+        add_fn!("det", 1..=1, |_, args| Ok(Value::Number(
+            matrix_arg(args, 0)?.det()?
+        )));
+        add_fn!("transpose", 1..=1, |_, args| match args.first() {
+            Some(Value::Vector(v)) => Ok(Value::Matrix(arithmetic::to_column(v)?)),
+            _ => Ok(Value::Matrix(matrix_arg(args, 0)?.transpose())),
         });
-        add_fn!("tanh", |args| {
-            match args.get(0) {
-                Some(Value::Number(value)) => Ok(Value::Number(value.tanh())),
-                _ => Err("expected a number".to_string()),
-            }
+        add_fn!("inv", 1..=1, |_, args| Ok(Value::Matrix(
+            matrix_arg(args, 0)?.inverse()?
+        )));
+        add_fn!("rank", 1..=1, |_, args| {
+            Ok(Value::from(matrix_arg(args, 0)?.rank() as i32))
         });
-        add_fn!("asinh", |args| {
-            match args.get(0) {
-                Some(Value::Number(value)) => Ok(Value::Number(value.asinh())),
-                _ => Err("expected a number".to_string()),
+        add_fn!("trace", 1..=1, |_, args| Ok(Value::Number(
+            matrix_arg(args, 0)?.trace()?
+        )));
+        add_fn!("solve", 2..=2, |interpreter, args| {
+            // solve(f, x0) looks for a root of f near x0
+            if let Some(function @ (Value::Function(_) | Value::NativeFunction(_))) = args.first() {
+                let Some(guess) = args[1].as_f64() else {
+                    return Err(args.error(
+                        1,
+                        "expected a real starting point, like solve(f, 1)".to_string(),
+                    ));
+                };
+                let mut f = |x: f64| -> Result<f64, NativeError> {
+                    Ok(interpreter
+                        .call(function, &[Value::Number(x)], args.range.clone())?
+                        .as_f64()
+                        .unwrap_or(f64::NAN))
+                };
+                return match solver::newton(&mut f, guess)? {
+                    Some(root) => Ok(Value::Vector(vec![Value::Number(root)])),
+                    None => Err(format!("couldn't find a root near {}", guess).into()),
+                };
             }
-        });
-        add_fn!("acosh", |args| {
-            match args.get(0) {
-                Some(Value::Number(value)) => Ok(Value::Number(value.acosh())),
-                _ => Err("expected a number".to_string()),
+
+            let a = matrix_arg(args, 0)?;
+            match args.get(1) {
+                Some(Value::Vector(b)) => Ok(arithmetic::from_column(
+                    &a.solve(&arithmetic::to_column(b)?)?,
+                )),
+                _ => Ok(Value::Matrix(a.solve(matrix_arg(args, 1)?)?)),
             }
         });
-        add_fn!("atanh", |args| {
-            match args.get(0) {
-                Some(Value::Number(value)) => Ok(Value::Number(value.atanh())),
-                _ => Err("expected a number".to_string()),
-            }
+        add_fn!("lu", 1..=1, |_, args| {
+            let (l, u, p) = matrix_arg(args, 0)?.lu()?;
+            Ok(Value::Vector(vec![
+                Value::Matrix(l),
+                Value::Matrix(u),
+                Value::Matrix(p),
+            ]))
+        });
+        add_fn!("qr", 1..=1, |_, args| {
+            let (q, r) = matrix_arg(args, 0)?.qr();
+            Ok(Value::Vector(vec![Value::Matrix(q), Value::Matrix(r)]))
+        });
+        add_fn!("eig", 1..=1, |_, args| {
+            let (values, vectors) = matrix_arg(args, 0)?.eig_symmetric()?;
+            Ok(Value::Vector(vec![
+                Value::Vector(values.into_iter().map(Value::Number).collect()),
+                Value::Matrix(vectors),
+            ]))
         });
 
-        add_fn!("gcd", |args| {
-            match (args.get(0), args.get(1)) {
-                (Some(Value::Number(a)), Some(Value::Number(b))) => Ok(Value::Number(gcd(*a, *b))),
-                _ => Err("expected 2 numbers".to_string()),
+        add_fn!("gcd", 2..=2, |_, args| match (&args[0], &args[1]) {
+            (Value::Integer(a), Value::Integer(b)) => Ok(Value::Integer(a.gcd(b))),
+            _ => {
+                let (a, b) = (number_arg(args, 0)?, number_arg(args, 1)?);
+                Ok(Value::Number(gcd(a, b)))
             }
         });
-        add_fn!("lcm", |args| {
-            match (args.get(0), args.get(1)) {
-                (Some(Value::Number(a)), Some(Value::Number(b))) => {
-                    Ok(Value::Number(a * b / gcd(*a, *b)))
-                }
-                _ => Err("expected 2 numbers".to_string()),
+        add_fn!("lcm", 2..=2, |_, args| match (&args[0], &args[1]) {
+            (Value::Integer(a), Value::Integer(b)) => Ok(Value::Integer(a.lcm(b))),
+            _ => {
+                let (a, b) = (number_arg(args, 0)?, number_arg(args, 1)?);
+                Ok(Value::Number(a * b / gcd(a, b)))
             }
         });
-        add_fn!("min", |args| {
-            if args.len() < 1 {
-                return Err("expected at least 1 argument".to_string());
-            }
-
-            let mut min = f64::INFINITY;
-            for arg in args {
-                match arg {
-                    Value::Number(x) => {
-                        if *x < min {
-                            min = *x;
-                        }
-                    }
-                    _ => {}
+        add_fn!("min", 1..=usize::MAX, |_, args| {
+            let mut min = &args[0];
+            for (i, arg) in args.iter().enumerate() {
+                let ordering =
+                    arithmetic::compare(arg, min).map_err(|reason| args.error(i, reason))?;
+                if ordering.is_some_and(|ordering| ordering.is_lt()) {
+                    min = arg;
                 }
             }
-            Ok(Value::Number(min))
+            Ok(min.clone())
         });
-        add_fn!("max", |args| {
-            if args.len() < 1 {
-                return Err("expected at least 1 argument".to_string());
-            }
-
-            let mut min = f64::NEG_INFINITY;
-            for arg in args {
-                match arg {
-                    Value::Number(x) => {
-                        if *x > min {
-                            min = *x;
-                        }
-                    }
-                    _ => {}
+        add_fn!("max", 1..=usize::MAX, |_, args| {
+            let mut max = &args[0];
+            for (i, arg) in args.iter().enumerate() {
+                let ordering =
+                    arithmetic::compare(arg, max).map_err(|reason| args.error(i, reason))?;
+                if ordering.is_some_and(|ordering| ordering.is_gt()) {
+                    max = arg;
                 }
             }
-            Ok(Value::Number(min))
+            Ok(max.clone())
         });
-        add_fn!("clamp", |args| {
-            match (args.get(0), args.get(1), args.get(2)) {
-                (Some(Value::Number(a)), Some(Value::Number(b)), Some(Value::Number(c))) => {
-                    Ok(Value::Number(a.max(*b).min(*c)))
-                }
-                _ => Err("expected 2 numbers".to_string()),
+        add_fn!("clamp", 3..=3, |_, args| {
+            let (x, min, max) = (&args[0], &args[1], &args[2]);
+            if arithmetic::compare(x, min)?.is_some_and(|ordering| ordering.is_lt()) {
+                Ok(min.clone())
+            } else if arithmetic::compare(x, max)?.is_some_and(|ordering| ordering.is_gt()) {
+                Ok(max.clone())
+            } else {
+                Ok(x.clone())
             }
         });
     }
 }
 
+fn number_arg(args: &Args, index: usize) -> Result<f64, NativeError> {
+    match args.get(index).map(|value| (value, value.as_f64())) {
+        Some((_, Some(x))) => Ok(x),
+        Some((value, None)) => {
+            Err(args.error(index, format!("expected a number, found {}", value)))
+        }
+        None => Err(args.error(index, "expected a number".to_string())),
+    }
+}
+
+fn matrix_arg<'a>(args: &'a Args, index: usize) -> Result<&'a Matrix, NativeError> {
+    match args.get(index) {
+        Some(Value::Matrix(matrix)) => Ok(matrix),
+        Some(value) => Err(args.error(index, format!("expected a matrix, found {}", value))),
+        None => Err(args.error(index, "expected a matrix".to_string())),
+    }
+}
+
+fn vector_arg<'a>(args: &'a Args, index: usize) -> Result<&'a [Value], NativeError> {
+    match args.get(index) {
+        Some(Value::Vector(elements)) => Ok(elements),
+        Some(value) => Err(args.error(index, format!("expected a vector, found {}", value))),
+        None => Err(args.error(index, "expected a vector".to_string())),
+    }
+}
+
+fn function_arg<'a>(args: &'a Args, index: usize) -> Result<&'a Value, NativeError> {
+    match args.get(index) {
+        Some(function @ (Value::Function(_) | Value::NativeFunction(_))) => Ok(function),
+        Some(value) => Err(args.error(index, format!("expected a function, found {}", value))),
+        None => Err(args.error(index, "expected a function".to_string())),
+    }
+}
+
 fn gcd(mut a: f64, mut b: f64) -> f64 {
     while b != 0.0 {
         let t = b;
@@ -703,3 +1265,34 @@ fn gcd(mut a: f64, mut b: f64) -> f64 {
     }
     a
 }
+
+/// Aitken's Δ² process: each estimate assumes the changes between three consecutive values
+/// shrink geometrically and jumps to their limit. With partial results after 2ⁿ terms, that
+/// removes the leading 1/kᵖ error of series like 1/k². Stops at the first ratio that isn't
+/// shrinking, since the values are diverging from there on.
+fn aitken(values: &[f64]) -> Vec<f64> {
+    let mut estimates = vec![];
+    for window in values.windows(3) {
+        let (first, second) = (window[1] - window[0], window[2] - window[1]);
+        if first == 0.0 || second == 0.0 {
+            estimates.push(window[2]);
+            continue;
+        }
+        let ratio = second / first;
+        if ratio.abs() >= 1.0 {
+            break;
+        }
+        estimates.push(window[2] + second * ratio / (1.0 - ratio));
+    }
+    estimates
+}
+
+/// Why a user function `name` that takes `expected` arguments can't be called with `given`
+fn arity_error(name: &str, expected: usize, given: usize) -> String {
+    format!(
+        "{} takes {}, but was given {}",
+        name,
+        plural(expected, "argument"),
+        given
+    )
+}
@@ -1,31 +1,56 @@
-use crate::{AmiError, Token, TokenType};
+use std::{iter::Peekable, ops::Range, str::CharIndices};
+
+use unicode_ident::{is_xid_continue, is_xid_start};
+
+use crate::{AmiError, BigInt, Comment, Token, TokenType};
 
 use TokenType::*;
 
-pub struct Lexer {
-    source: String,
+pub struct Lexer<'a> {
+    source: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    /// The byte offset of `current_char`, or the length of the source at the end
     index: usize,
     current_char: char,
+    /// The character after `current_char`, for two character tokens like `<=`
+    next_char: char,
+    len: usize,
+    /// Whether the last token can end a statement, like a number or a closing bracket
+    can_end_statement: bool,
+    /// The brackets around the current token, innermost last
+    groups: Vec<TokenType>,
 }
 
 const SUPERSCRIPT: &str = "ᵃᵇᶜᵈᵉᶠᵍʰⁱʲᵏˡᵐⁿᵒᵖʳˢᵗᵘᵛʷˣʸᶻᴬᴮᶜᴰᴱᶠᴳᴴᴵᴶᴷᴸᴹᴺᴼᴾᴿˢᵀᵁⱽᵂˣʸᶻ⁰¹²³⁴⁵⁶⁷⁸⁹⁺⁻⁼⁽⁾";
 const NORMALSCRIPT: &str = "abcdefghijklmnoprstuvwxyzABCDEFGHIJKLMNOPRSTUVWXYZ0123456789+-=()";
+const SUBSCRIPT: &str = "ₐₑₕᵢⱼₖₗₘₙₒₚᵣₛₜᵤᵥₓ₀₁₂₃₄₅₆₇₈₉₊₋₌₍₎";
+const NORMAL_SUBSCRIPT: &str = "aehijklmnoprstuvx0123456789+-=()";
 
 type LexResult = Result<Token, AmiError>;
 
-impl Lexer {
-    pub fn new(source: String) -> Self {
-        Self {
-            index: 0,
-            current_char: source.chars().nth(0).unwrap_or('\0'),
+impl<'a> Lexer<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let mut lexer = Self {
             source,
-        }
+            chars: source.char_indices().peekable(),
+            index: 0,
+            current_char: '\0',
+            next_char: '\0',
+            len: source.len(),
+            can_end_statement: false,
+            groups: vec![],
+        };
+        lexer.advance();
+        lexer
     }
 
     fn advance(&mut self) {
-        self.index += 1;
-        let ch = self.source.chars().nth(self.index).unwrap_or('\0');
-        self.current_char = ch;
+        (self.index, self.current_char) = self.chars.next().unwrap_or((self.len, '\0'));
+        self.next_char = self.chars.peek().map_or('\0', |&(_, ch)| ch);
+    }
+
+    fn peek(&self) -> char {
+        self.next_char
     }
 
     fn error(&self, msg: String, reason: String, start: usize) -> LexResult {
@@ -48,223 +73,501 @@ impl Lexer {
     }
 
     pub fn next_token(&mut self) -> LexResult {
-        while matches!(self.current_char, ' ' | '\t' | '\r') {
-            self.advance();
+        let comments = self.trivia()?;
+        // A block comment over several lines ends the statement before it, like a line
+        // comment and its newline would. Anywhere else it's only attached to the next token.
+        let mut token = if comments.iter().any(|comment| comment.text.contains('\n'))
+            && self.can_end_statement
+            && !matches!(
+                self.groups.last(),
+                Some(LeftParen | LeftAngle | LeftFloor | LeftCeil)
+            ) {
+            Token::new(Newline, self.index..self.index)
+        } else {
+            self.token()?
+        };
+        token.comments = comments;
+
+        self.can_end_statement = matches!(
+            token.ty,
+            Number(_)
+                | Identifier(_)
+                | Superscript(_)
+                | Subscript(_)
+                | Degree
+                | Exclamation
+                | RightParen
+                | RightBrace
+                | RightBracket
+                | RightAngle
+                | Pipe
+                | RightFloor
+                | RightCeil
+        );
+        match token.ty {
+            LeftParen | LeftBrace | LeftBracket | LeftAngle | LeftFloor | LeftCeil => {
+                self.groups.push(token.ty.clone())
+            }
+            RightParen | RightBrace | RightBracket | RightAngle | RightFloor | RightCeil => {
+                self.groups.pop();
+            }
+            _ => {}
+        }
+        Ok(token)
+    }
+
+    /// Skips whitespace and comments, returning the comments
+    fn trivia(&mut self) -> Result<Vec<Comment>, AmiError> {
+        let mut comments = vec![];
+        loop {
+            let start = self.index;
+            match (self.current_char, self.peek()) {
+                (' ' | '\t' | '\r', _) => self.advance(),
+                ('#', _) | ('/', '/') => {
+                    // The newline is left as a token, since it ends the statement
+                    while !matches!(
+                        (self.current_char, self.peek()),
+                        ('\n' | '\0', _) | ('\r', '\n')
+                    ) {
+                        self.advance();
+                    }
+                    comments.push(self.comment(start));
+                }
+                ('/', '*') => {
+                    self.block_comment()?;
+                    comments.push(self.comment(start));
+                }
+                _ => return Ok(comments),
+            }
+        }
+    }
+
+    /// Skips a `/* */` comment, which can have others nested inside it
+    fn block_comment(&mut self) -> Result<(), AmiError> {
+        let start = self.index;
+        let mut depth = 0;
+        loop {
+            match (self.current_char, self.peek()) {
+                ('/', '*') => {
+                    depth += 1;
+                    self.advance();
+                    self.advance();
+                }
+                ('*', '/') => {
+                    depth -= 1;
+                    self.advance();
+                    self.advance();
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                ('\0', _) => {
+                    return Err(AmiError {
+                        msg: "unterminated comment".to_string(),
+                        reason: "this comment is never closed with */".to_string(),
+                        range: start..start + 2,
+                    })
+                }
+                _ => self.advance(),
+            }
+        }
+    }
+
+    fn comment(&self, start: usize) -> Comment {
+        Comment {
+            text: self.source[start..self.index].into(),
+            range: start..self.index,
         }
+    }
 
+    fn token(&mut self) -> LexResult {
         let start = self.index;
         match self.current_char {
             '0'..='9' => self.number(),
-            'a'..='z' | 'A'..='Z' | '_' | 'Α'..='ω' | '∞' => self.word(),
-            ch if SUPERSCRIPT.contains(ch) => self.superscript(),
+            // These would otherwise lex as Greek identifiers
+            'Σ' | '∑' => {
+                self.advance();
+                Ok(Token::new(Sum, start..self.index))
+            }
+            'Π' | '∏' => {
+                self.advance();
+                Ok(Token::new(Product, start..self.index))
+            }
+            ch if is_identifier_start(ch) => self.word(),
+            ch if SUPERSCRIPT.contains(ch) => {
+                let tokens = self.script(SUPERSCRIPT, NORMALSCRIPT)?;
+                Ok(Token::new(Superscript(tokens), start..self.index))
+            }
+            ch if SUBSCRIPT.contains(ch) => {
+                let tokens = self.script(SUBSCRIPT, NORMAL_SUBSCRIPT)?;
+                Ok(Token::new(Subscript(tokens), start..self.index))
+            }
             '=' => {
                 self.advance();
-                Ok(Token {
-                    ty: Eq,
-                    range: start..self.index,
-                })
+                Ok(Token::new(Eq, start..self.index))
+            }
+            '≠' => {
+                self.advance();
+                Ok(Token::new(Ne, start..self.index))
+            }
+            '!' if self.peek() == '=' => {
+                self.advance();
+                self.advance();
+                Ok(Token::new(Ne, start..self.index))
+            }
+            '<' if self.peek() == '=' => {
+                self.advance();
+                self.advance();
+                Ok(Token::new(Le, start..self.index))
+            }
+            '<' => {
+                self.advance();
+                Ok(Token::new(Lt, start..self.index))
+            }
+            '≤' => {
+                self.advance();
+                Ok(Token::new(Le, start..self.index))
+            }
+            '>' if self.peek() == '=' => {
+                self.advance();
+                self.advance();
+                Ok(Token::new(Ge, start..self.index))
+            }
+            '>' => {
+                self.advance();
+                Ok(Token::new(Gt, start..self.index))
+            }
+            '≥' => {
+                self.advance();
+                Ok(Token::new(Ge, start..self.index))
+            }
+            '∧' => {
+                self.advance();
+                Ok(Token::new(And, start..self.index))
+            }
+            '∨' => {
+                self.advance();
+                Ok(Token::new(Or, start..self.index))
+            }
+            '¬' => {
+                self.advance();
+                Ok(Token::new(Not, start..self.index))
             }
             '+' => {
                 self.advance();
-                Ok(Token {
-                    ty: Plus,
-                    range: start..self.index,
-                })
+                Ok(Token::new(Plus, start..self.index))
+            }
+            '-' if self.peek() == '>' => {
+                self.advance();
+                self.advance();
+                Ok(Token::new(Arrow, start..self.index))
+            }
+            '↦' => {
+                self.advance();
+                Ok(Token::new(Arrow, start..self.index))
             }
             '-' => {
                 self.advance();
-                Ok(Token {
-                    ty: Minus,
-                    range: start..self.index,
-                })
+                Ok(Token::new(Minus, start..self.index))
             }
             '*' => {
                 self.advance();
-                Ok(Token {
-                    ty: Star,
-                    range: start..self.index,
-                })
+                Ok(Token::new(Star, start..self.index))
             }
-            '∙' => {
+            '∙' | '·' | '⋅' => {
                 self.advance();
-                Ok(Token {
-                    ty: Dot,
-                    range: start..self.index,
-                })
+                Ok(Token::new(Dot, start..self.index))
             }
             '×' => {
                 self.advance();
-                Ok(Token {
-                    ty: Cross,
-                    range: start..self.index,
-                })
+                Ok(Token::new(Cross, start..self.index))
             }
             '/' => {
                 self.advance();
-                Ok(Token {
-                    ty: Slash,
-                    range: start..self.index,
-                })
+                Ok(Token::new(Slash, start..self.index))
             }
             '÷' => {
                 self.advance();
-                Ok(Token {
-                    ty: Divide,
-                    range: start..self.index,
-                })
+                Ok(Token::new(Divide, start..self.index))
             }
             '%' => {
                 self.advance();
-                Ok(Token {
-                    ty: Percent,
-                    range: start..self.index,
-                })
+                Ok(Token::new(Percent, start..self.index))
             }
             '^' => {
                 self.advance();
-                Ok(Token {
-                    ty: Carrot,
-                    range: start..self.index,
-                })
+                Ok(Token::new(Carrot, start..self.index))
             }
             '√' => {
                 self.advance();
-                Ok(Token {
-                    ty: Sqrt,
-                    range: start..self.index,
-                })
+                Ok(Token::new(Sqrt, start..self.index))
             }
             '∛' => {
                 self.advance();
-                Ok(Token {
-                    ty: Cbrt,
-                    range: start..self.index,
-                })
+                Ok(Token::new(Cbrt, start..self.index))
             }
             '∜' => {
                 self.advance();
-                Ok(Token {
-                    ty: Fort,
-                    range: start..self.index,
-                })
+                Ok(Token::new(Fort, start..self.index))
+            }
+            '∘' => {
+                self.advance();
+                Ok(Token::new(Compose, start..self.index))
+            }
+            '∫' => {
+                self.advance();
+                Ok(Token::new(Integral, start..self.index))
             }
             '°' => {
                 self.advance();
-                Ok(Token {
-                    ty: Degree,
-                    range: start..self.index,
-                })
+                Ok(Token::new(Degree, start..self.index))
             }
             '!' => {
                 self.advance();
-                Ok(Token {
-                    ty: Exclamation,
-                    range: start..self.index,
-                })
+                Ok(Token::new(Exclamation, start..self.index))
             }
             '(' => {
                 self.advance();
-                Ok(Token {
-                    ty: LeftParen,
-                    range: start..self.index,
-                })
+                Ok(Token::new(LeftParen, start..self.index))
             }
             ')' => {
                 self.advance();
-                Ok(Token {
-                    ty: RightParen,
-                    range: start..self.index,
-                })
+                Ok(Token::new(RightParen, start..self.index))
             }
             '{' => {
                 self.advance();
-                Ok(Token {
-                    ty: LeftBrace,
-                    range: start..self.index,
-                })
+                Ok(Token::new(LeftBrace, start..self.index))
             }
             '}' => {
                 self.advance();
-                Ok(Token {
-                    ty: RightBrace,
-                    range: start..self.index,
-                })
+                Ok(Token::new(RightBrace, start..self.index))
+            }
+            '[' => {
+                self.advance();
+                Ok(Token::new(LeftBracket, start..self.index))
+            }
+            ']' => {
+                self.advance();
+                Ok(Token::new(RightBracket, start..self.index))
+            }
+            '⟨' => {
+                self.advance();
+                Ok(Token::new(LeftAngle, start..self.index))
+            }
+            '⟩' => {
+                self.advance();
+                Ok(Token::new(RightAngle, start..self.index))
             }
             '|' => {
                 self.advance();
-                Ok(Token {
-                    ty: Pipe,
-                    range: start..self.index,
-                })
+                Ok(Token::new(Pipe, start..self.index))
             }
             '⌊' => {
                 self.advance();
-                Ok(Token {
-                    ty: LeftFloor,
-                    range: start..self.index,
-                })
+                Ok(Token::new(LeftFloor, start..self.index))
             }
             '⌋' => {
                 self.advance();
-                Ok(Token {
-                    ty: RightFloor,
-                    range: start..self.index,
-                })
+                Ok(Token::new(RightFloor, start..self.index))
             }
             '⌈' => {
                 self.advance();
-                Ok(Token {
-                    ty: LeftCeil,
-                    range: start..self.index,
-                })
+                Ok(Token::new(LeftCeil, start..self.index))
             }
             '⌉' => {
                 self.advance();
-                Ok(Token {
-                    ty: RightCeil,
-                    range: start..self.index,
-                })
+                Ok(Token::new(RightCeil, start..self.index))
             }
             ',' => {
                 self.advance();
-                Ok(Token {
-                    ty: Comma,
-                    range: start..self.index,
-                })
+                Ok(Token::new(Comma, start..self.index))
+            }
+            ';' => {
+                self.advance();
+                Ok(Token::new(Semicolon, start..self.index))
             }
             '\n' => {
                 self.advance();
-                Ok(Token {
-                    ty: Newline,
-                    range: start..self.index,
-                })
-            }
-            '\0' => Ok(Token {
-                ty: EOF,
-                range: start..self.index,
-            }),
-            _ => self.error(
-                "invalid character".to_string(),
-                format!("'{}' is not a valid character", self.current_char),
-                start,
-            ),
+                Ok(Token::new(Newline, start..self.index))
+            }
+            '\0' => Ok(Token::new(EOF, start..self.index)),
+            ch => {
+                self.advance();
+                self.error(
+                    "invalid character".to_string(),
+                    format!("'{}' is not a valid character", ch),
+                    start,
+                )
+            }
         }
     }
 
     fn number(&mut self) -> LexResult {
         let start = self.index;
-        let mut num_str = self.current_char.to_string();
-        self.advance();
+        if self.current_char == '0' {
+            let radix = match self.peek() {
+                'x' | 'X' => Some((16, "hexadecimal")),
+                'o' | 'O' => Some((8, "octal")),
+                'b' | 'B' => Some((2, "binary")),
+                _ => None,
+            };
+            if let Some((radix, name)) = radix {
+                self.advance();
+                self.advance();
+                if !self.current_char.is_digit(radix) {
+                    // Point at the character that should have been a digit, or at the prefix
+                    // when there's nothing there
+                    let digit_start = match self.current_char {
+                        ch if ch.is_alphanumeric() => {
+                            let digit_start = self.index;
+                            self.advance();
+                            digit_start
+                        }
+                        _ => start,
+                    };
+                    return self.error(
+                        "invalid number".to_string(),
+                        format!("expected {} digit", article(name)),
+                        digit_start,
+                    );
+                }
+                return self.radix_number(start, radix, name);
+            }
+        }
 
-        while "0123456789.".contains(self.current_char) {
-            num_str.push(self.current_char);
+        let mut num_str = String::new();
+        self.digits(&mut num_str, 10)?;
+        if self.current_char == '.' {
+            num_str.push('.');
             self.advance();
+            self.digits(&mut num_str, 10)?;
+            if self.current_char == '.' {
+                while self.current_char == '.' || self.current_char.is_ascii_digit() {
+                    self.advance();
+                }
+                return self.error(
+                    "invalid number".to_string(),
+                    "a number can only have one decimal point".to_string(),
+                    start,
+                );
+            }
         }
 
-        Ok(Token {
-            ty: Number(num_str.into()),
-            range: start..self.index,
-        })
+        let has_exponent = match (self.current_char, self.peek()) {
+            ('e' | 'E', '+' | '-') => self.nth(2).is_ascii_digit(),
+            ('e' | 'E', ch) => ch.is_ascii_digit(),
+            _ => false,
+        };
+        if has_exponent {
+            num_str.push('e');
+            self.advance();
+            if let '+' | '-' = self.current_char {
+                num_str.push(self.current_char);
+                self.advance();
+            }
+            self.digits(&mut num_str, 10)?;
+            if self.current_char == '.' {
+                let dot = self.index;
+                self.advance();
+                return self.error(
+                    "invalid number".to_string(),
+                    "exponents must be whole numbers".to_string(),
+                    dot,
+                );
+            }
+        } else if let Some(exponent) = self.superscript_exponent() {
+            num_str.push('e');
+            num_str.push_str(&exponent);
+        }
+
+        Ok(Token::new(Number(num_str.into()), start..self.index))
+    }
+
+    /// The character `n` characters after `current_char`
+    fn nth(&self, n: usize) -> char {
+        match n {
+            0 => self.current_char,
+            _ => self.chars.clone().nth(n - 1).map_or('\0', |(_, ch)| ch),
+        }
+    }
+
+    /// Pushes a run of digits in `radix` onto `text`, skipping the underscores that can
+    /// separate them, like in `1_000_000`
+    fn digits(&mut self, text: &mut String, radix: u32) -> Result<(), AmiError> {
+        while self.current_char.is_digit(radix) {
+            text.push(self.current_char);
+            self.advance();
+            if self.current_char == '_' {
+                let start = self.index;
+                self.advance();
+                if !self.current_char.is_digit(radix) {
+                    return Err(AmiError {
+                        msg: "invalid number".to_string(),
+                        reason: "an underscore in a number has to be between two digits"
+                            .to_string(),
+                        range: start..self.index,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// A hexadecimal, octal or binary integer, after its prefix. It becomes a token with its
+    /// decimal digits, so the rest of the interpreter doesn't need to know about it.
+    fn radix_number(&mut self, start: usize, radix: u32, name: &str) -> LexResult {
+        let mut digits = String::new();
+        self.digits(&mut digits, radix)?;
+        if self.current_char.is_alphanumeric() || self.current_char == '.' {
+            let ch = self.current_char;
+            let bad_start = self.index;
+            self.advance();
+            return self.error(
+                "invalid number".to_string(),
+                format!("'{}' is not {} digit", ch, article(name)),
+                bad_start,
+            );
+        }
+
+        let radix_big = BigInt::from(radix);
+        let value = digits.chars().fold(BigInt::zero(), |value, digit| {
+            &(&value * &radix_big) + &BigInt::from(digit.to_digit(radix).unwrap())
+        });
+        Ok(Token::new(
+            Number(value.to_string().into()),
+            start..self.index,
+        ))
+    }
+
+    /// The exponent of a number written like `6.022×10²³`, which is consumed if it's there
+    fn superscript_exponent(&mut self) -> Option<String> {
+        if self.current_char != '×' || self.peek() != '1' || self.nth(2) != '0' {
+            return None;
+        }
+
+        let mut exponent = String::new();
+        let mut n = 3;
+        loop {
+            match self.nth(n) {
+                '⁺' if n == 3 => {}
+                '⁻' if n == 3 => exponent.push('-'),
+                ch => match "⁰¹²³⁴⁵⁶⁷⁸⁹".chars().position(|digit| digit == ch) {
+                    Some(digit) => exponent.push(char::from(b'0' + digit as u8)),
+                    None => break,
+                },
+            }
+            n += 1;
+        }
+        // Anything else in superscript means it's a more complicated power, like `10²ˣ`
+        if !exponent.ends_with(|ch: char| ch.is_ascii_digit()) || SUPERSCRIPT.contains(self.nth(n))
+        {
+            return None;
+        }
+
+        for _ in 0..n {
+            self.advance();
+        }
+        Some(exponent)
     }
 
     fn word(&mut self) -> LexResult {
@@ -272,46 +575,108 @@ impl Lexer {
         let mut word = self.current_char.to_string();
         self.advance();
 
-        while self.current_char != '\0' {
-            match self.current_char {
-                'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | 'Α'..='ω' | '∞' => {
-                    word.push(self.current_char);
-                    self.advance();
-                }
-                _ => break,
-            };
+        // Subscripts are part of the name too, so x₁ and aₙ are variables of their own
+        while is_identifier_continue(self.current_char) {
+            word.push(self.current_char);
+            self.advance();
+        }
+        // Primes are part of the name, so f' is the derivative of f
+        while matches!(self.current_char, '\'' | '′') {
+            word.push(self.current_char);
+            self.advance();
         }
 
-        Ok(Token {
-            ty: match word.as_str() {
-                "mod" => Mod,
-                _ => Identifier(word.into()),
-            },
-            range: start..self.index,
-        })
+        let ty = match word.as_str() {
+            "mod" => Mod,
+            "and" => And,
+            "or" => Or,
+            "not" => Not,
+            "if" => If,
+            "then" => Then,
+            "else" => Else,
+            "otherwise" => Otherwise,
+            "true" => Bool(true),
+            "false" => Bool(false),
+            _ => Identifier(word.into()),
+        };
+        Ok(Token::new(ty, start..self.index))
     }
 
-    fn superscript(&mut self) -> LexResult {
-        let start = self.index;
+    /// Lexes a run of superscript or subscript characters as the tokens they spell out, with
+    /// ranges that point at the script characters in the source
+    fn script(&mut self, script: &str, normal: &str) -> Result<Vec<Token>, AmiError> {
         let mut source = String::new();
+        // Where each character of `source` came from. They're all ASCII, so byte offsets
+        // into `source` index this directly.
+        let mut origins: Vec<Range<usize>> = vec![];
 
         while self.current_char != '\0' {
-            match SUPERSCRIPT.chars().position(|ch| ch == self.current_char) {
+            match script.chars().position(|ch| ch == self.current_char) {
                 Some(index) => {
-                    let normal_char = NORMALSCRIPT.chars().nth(index).unwrap();
+                    let normal_char = normal.chars().nth(index).unwrap();
                     source.push(normal_char);
+                    let start = self.index;
                     self.advance();
+                    origins.push(start..self.index);
                 }
                 None => break,
             };
         }
 
-        let mut lexer = Lexer::new(source);
-        let mut tokens = lexer.lex()?;
+        let end = self.index;
+        let origin = |range: Range<usize>| {
+            let start = origins.get(range.start).map_or(end, |origin| origin.start);
+            match range.end.checked_sub(1).and_then(|last| origins.get(last)) {
+                Some(last) if range.end > range.start => start..last.end,
+                _ => start..start,
+            }
+        };
+
+        let mut lexer = Lexer::new(&source);
+        let mut tokens = lexer.lex().map_err(|error| AmiError {
+            range: origin(error.range.clone()),
+            ..error
+        })?;
         tokens.pop();
-        Ok(Token {
-            ty: Superscript(tokens),
-            range: start..self.index,
-        })
+        for token in &mut tokens {
+            token.range = origin(token.range.clone());
+        }
+        Ok(tokens)
+    }
+}
+
+/// `name` with "a" or "an" in front of it
+fn article(name: &str) -> String {
+    if name.starts_with(['a', 'e', 'i', 'o', 'u']) {
+        format!("an {}", name)
+    } else {
+        format!("a {}", name)
+    }
+}
+
+/// Letters like 𝑥 and 𝜑 from the Mathematical Alphanumeric Symbols block, which also has
+/// symbols like 𝛁 that aren't letters but are still used as names
+fn is_math_alphanumeric(ch: char) -> bool {
+    ('\u{1D400}'..='\u{1D7FF}').contains(&ch)
+}
+
+/// Whether an identifier can start with `ch`. Identifiers follow Unicode's rules for them,
+/// except that superscripts and subscripts are lexed on their own.
+fn is_identifier_start(ch: char) -> bool {
+    match ch {
+        '_' | '∞' => true,
+        ch if SUPERSCRIPT.contains(ch) || SUBSCRIPT.contains(ch) => false,
+        ch => is_xid_start(ch) || is_math_alphanumeric(ch) && !ch.is_numeric(),
+    }
+}
+
+fn is_identifier_continue(ch: char) -> bool {
+    match ch {
+        '∞' => true,
+        // A multiplication sign, even though Unicode lets it be part of a name
+        '·' => false,
+        ch if SUBSCRIPT.contains(ch) => true,
+        ch if SUPERSCRIPT.contains(ch) => false,
+        ch => is_xid_continue(ch) || is_math_alphanumeric(ch),
     }
 }
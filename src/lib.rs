@@ -0,0 +1,55 @@
+//! An interpreter for math written the way it's written on paper, like `∫₀^π sin(x) dx` or
+//! `solve(x² - 2 = 0, x)`.
+//!
+//! [`eval`] runs a one-off expression, and an [`Engine`] keeps variables and functions between
+//! runs and lets the host program add its own.
+//!
+//! ```
+//! let area = ami::eval("∫₀^π sin(x) dx").unwrap();
+//! assert_eq!(area.to_string(), "2");
+//!
+//! let mut engine = ami::Engine::new();
+//! engine.eval("f(x) = x² - 2").unwrap();
+//! let root = engine.eval("solve(f(x) = 0, x)").unwrap();
+//! assert_eq!(root.to_string(), "[-1.414213562373095, 1.4142135623730951]");
+//! ```
+//!
+//! `solve(equation, x)` finds every root of a polynomial, complex ones included. Any other
+//! equation is solved numerically, which only finds its real roots between -100 and 100, so
+//! `solve(sin(x) = 0, x)` gives the multiples of π in that range.
+
+mod arithmetic;
+mod bigint;
+mod complex;
+mod derivative;
+mod engine;
+mod error;
+mod interpreter;
+mod lexer;
+mod math;
+mod matrix;
+mod native;
+mod node;
+mod parser;
+mod rational;
+mod scope;
+mod simplify;
+mod solver;
+mod token;
+mod value;
+
+pub use bigint::BigInt;
+pub use complex::Complex;
+pub use engine::{eval, Diagnostic, Engine};
+pub use error::AmiError;
+pub use interpreter::Interpreter;
+pub use matrix::Matrix;
+pub use native::{Args, NativeError, NativeFunction};
+pub use rational::Rational;
+pub use value::{Function, Value};
+
+use lexer::*;
+use node::*;
+use parser::*;
+use scope::*;
+use token::*;
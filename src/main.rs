@@ -1,4 +1,4 @@
-use ariadne::{Color, Label, Report, ReportKind, Source};
+use ami::{AmiError, Engine};
 use clap::{self, Parser};
 use std::{
     fs,
@@ -6,23 +6,6 @@ use std::{
     path::Path,
 };
 
-mod error;
-mod interpreter;
-mod lexer;
-mod node;
-mod parser;
-mod scope;
-mod token;
-mod value;
-
-pub use error::*;
-pub use interpreter::*;
-pub use lexer::*;
-pub use node::*;
-pub use scope::*;
-pub use token::*;
-pub use value::*;
-
 #[derive(clap::Parser)]
 struct Arguments {
     /// The file to run
@@ -30,23 +13,28 @@ struct Arguments {
     /// Verbose mode
     #[arg(short, long)]
     verbose: bool,
+    /// Show complex numbers in polar form
+    #[arg(short, long)]
+    polar: bool,
 }
 
 fn main() {
     let args = Arguments::parse();
+    let mut engine = Engine::new();
+    if args.verbose {
+        engine.on_diagnostic(|diagnostic| println!("{}", diagnostic));
+    }
 
-    match args.file {
+    match &args.file {
         Some(file) => {
             let path = Path::new(&file);
             let input = fs::read_to_string(path).expect("Could not read file");
-            run(input, args.verbose, &mut Interpreter::default());
+            run(input, &args, &mut engine);
         }
         None => {
             let stdin = io::stdin();
             let mut stdout = io::stdout();
 
-            let mut interpreter = Interpreter::default();
-
             loop {
                 write!(&stdout, "> ").expect("Could not show prompt");
 
@@ -59,64 +47,25 @@ fn main() {
                     return;
                 }
 
-                run(input, args.verbose, &mut interpreter);
+                run(input, &args, &mut engine);
             }
         }
     }
 }
 
-fn run(input: String, verbose: bool, interpreter: &mut Interpreter) {
-    let mut lexer = Lexer::new(input.clone());
-    match lexer.lex() {
-        Ok(tokens) => {
-            if verbose {
-                println!(
-                    "tokens: {}",
-                    tokens
-                        .iter()
-                        .map(|t| t.to_string())
-                        .collect::<Vec<_>>()
-                        .join(" ")
-                );
+fn run(input: String, args: &Arguments, engine: &mut Engine) {
+    match engine.eval(&input) {
+        Ok(value) => {
+            if args.polar {
+                println!("{:#}", value);
+            } else {
+                println!("{}", value);
             }
-
-            let mut parser = parser::Parser::new(tokens);
-            match parser.parse() {
-                Ok(ast) => {
-                    if verbose {
-                        println!("AST: {}", ast);
-                    }
-
-                    let value = interpreter.run(ast);
-                    match value {
-                        Ok(value) => {
-                            println!("{}", value);
-                        }
-                        Err(e) => {
-                            print_error(e, &input);
-                        }
-                    }
-                }
-                Err(e) => {
-                    print_error(e, &input);
-                }
-            }
-        }
-        Err(e) => {
-            print_error(e, &input);
         }
+        Err(e) => print_error(e, &input),
     }
 }
 
 fn print_error(error: AmiError, input: &str) {
-    Report::build(ReportKind::Error, error.range.clone())
-        .with_message(&error.msg)
-        .with_label(
-            Label::new(error.range)
-                .with_color(Color::Red)
-                .with_message(&error.reason),
-        )
-        .finish()
-        .eprint(Source::from(input))
-        .unwrap();
+    eprint!("{}", error.render(input, true));
 }
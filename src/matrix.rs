@@ -0,0 +1,425 @@
+use std::fmt;
+
+/// A dense matrix of real numbers, stored row by row
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix {
+    rows: usize,
+    cols: usize,
+    data: Vec<f64>,
+}
+
+impl Matrix {
+    /// Returns `None` unless `data` has exactly `rows × cols` entries
+    pub fn new(rows: usize, cols: usize, data: Vec<f64>) -> Option<Self> {
+        (data.len() == rows * cols).then_some(Self { rows, cols, data })
+    }
+
+    pub fn zeros(rows: usize, cols: usize) -> Self {
+        Self {
+            rows,
+            cols,
+            data: vec![0.0; rows * cols],
+        }
+    }
+
+    pub fn identity(n: usize) -> Self {
+        let mut matrix = Self::zeros(n, n);
+        for i in 0..n {
+            matrix[(i, i)] = 1.0;
+        }
+        matrix
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn shape(&self) -> String {
+        format!("{}×{}", self.rows, self.cols)
+    }
+
+    pub fn is_square(&self) -> bool {
+        self.rows == self.cols
+    }
+
+    pub fn row(&self, i: usize) -> Vec<f64> {
+        self.data[i * self.cols..(i + 1) * self.cols].to_vec()
+    }
+
+    pub fn col(&self, j: usize) -> Vec<f64> {
+        (0..self.rows).map(|i| self[(i, j)]).collect()
+    }
+
+    pub fn map(&self, f: impl Fn(f64) -> f64) -> Self {
+        Self {
+            rows: self.rows,
+            cols: self.cols,
+            data: self.data.iter().map(|x| f(*x)).collect(),
+        }
+    }
+
+    pub fn zip_with(&self, other: &Self, f: impl Fn(f64, f64) -> f64) -> Result<Self, String> {
+        if self.rows != other.rows || self.cols != other.cols {
+            return Err(format!(
+                "matrices have different shapes, {} and {}",
+                self.shape(),
+                other.shape()
+            ));
+        }
+        Ok(Self {
+            rows: self.rows,
+            cols: self.cols,
+            data: self
+                .data
+                .iter()
+                .zip(&other.data)
+                .map(|(a, b)| f(*a, *b))
+                .collect(),
+        })
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut result = Self::zeros(self.cols, self.rows);
+        for i in 0..self.rows {
+            for j in 0..self.cols {
+                result[(j, i)] = self[(i, j)];
+            }
+        }
+        result
+    }
+
+    pub fn mul(&self, other: &Self) -> Result<Self, String> {
+        if self.cols != other.rows {
+            return Err(format!(
+                "cannot multiply a {} matrix by a {} matrix",
+                self.shape(),
+                other.shape()
+            ));
+        }
+
+        let mut result = Self::zeros(self.rows, other.cols);
+        for i in 0..self.rows {
+            for j in 0..other.cols {
+                result[(i, j)] = (0..self.cols).map(|k| self[(i, k)] * other[(k, j)]).sum();
+            }
+        }
+        Ok(result)
+    }
+
+    /// How small an entry can be before it counts as zero, like a pivot of a singular matrix.
+    /// It scales with the entries, so `[1e-13, 0; 0, 1e-13]` is as invertible as the identity.
+    fn tolerance(&self) -> f64 {
+        let largest = self
+            .data
+            .iter()
+            .fold(0.0_f64, |largest, x| largest.max(x.abs()));
+        self.rows.max(self.cols) as f64 * f64::EPSILON * largest
+    }
+
+    fn require_square(&self, operation: &str) -> Result<(), String> {
+        if !self.is_square() {
+            return Err(format!(
+                "{} needs a square matrix, not {}",
+                operation,
+                self.shape()
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn pow(&self, exponent: i64) -> Result<Self, String> {
+        self.require_square("raising to a power")?;
+
+        let mut base = if exponent < 0 {
+            self.inverse()?
+        } else {
+            self.clone()
+        };
+        let mut result = Self::identity(self.rows);
+        let mut n = exponent.unsigned_abs();
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result.mul(&base)?;
+            }
+            base = base.mul(&base)?;
+            n >>= 1;
+        }
+        Ok(result)
+    }
+
+    pub fn trace(&self) -> Result<f64, String> {
+        self.require_square("the trace")?;
+        Ok((0..self.rows).map(|i| self[(i, i)]).sum())
+    }
+
+    /// LU decomposition with partial pivoting, returning `(L, U, P)` where `PA = LU`
+    pub fn lu(&self) -> Result<(Self, Self, Self), String> {
+        self.require_square("LU decomposition")?;
+
+        let n = self.rows;
+        let tolerance = self.tolerance();
+        let mut u = self.clone();
+        let mut l = Self::identity(n);
+        let mut p = Self::identity(n);
+
+        for k in 0..n {
+            let pivot = (k..n)
+                .max_by(|a, b| u[(*a, k)].abs().total_cmp(&u[(*b, k)].abs()))
+                .unwrap();
+            if pivot != k {
+                u.swap_rows(k, pivot);
+                p.swap_rows(k, pivot);
+                for j in 0..k {
+                    let tmp = l[(k, j)];
+                    l[(k, j)] = l[(pivot, j)];
+                    l[(pivot, j)] = tmp;
+                }
+            }
+
+            if u[(k, k)].abs() <= tolerance {
+                continue;
+            }
+            for i in k + 1..n {
+                let factor = u[(i, k)] / u[(k, k)];
+                l[(i, k)] = factor;
+                for j in k..n {
+                    u[(i, j)] -= factor * u[(k, j)];
+                }
+            }
+        }
+
+        Ok((l, u, p))
+    }
+
+    pub fn det(&self) -> Result<f64, String> {
+        let (_, u, p) = self.lu()?;
+
+        // Each row swap in P flips the sign
+        let mut sign = 1.0;
+        let mut permutation: Vec<usize> = (0..self.rows)
+            .map(|i| (0..self.rows).find(|j| p[(i, *j)] == 1.0).unwrap())
+            .collect();
+        for i in 0..permutation.len() {
+            while permutation[i] != i {
+                let j = permutation[i];
+                permutation.swap(i, j);
+                sign = -sign;
+            }
+        }
+
+        Ok(sign * (0..self.rows).map(|i| u[(i, i)]).product::<f64>())
+    }
+
+    /// Solves `AX = B` for `X`, where `B` can have any number of columns
+    pub fn solve(&self, b: &Self) -> Result<Self, String> {
+        if b.rows != self.rows {
+            return Err(format!(
+                "cannot solve a {} system with a right-hand side of {}",
+                self.shape(),
+                b.shape()
+            ));
+        }
+
+        let (l, u, p) = self.lu()?;
+        let n = self.rows;
+        let tolerance = self.tolerance();
+        if (0..n).any(|i| u[(i, i)].abs() <= tolerance) {
+            return Err("the matrix is singular".to_string());
+        }
+
+        let pb = p.mul(b)?;
+        let mut x = Self::zeros(n, b.cols);
+        for c in 0..b.cols {
+            // Forward substitution for Ly = Pb, then back substitution for Ux = y
+            let mut y = vec![0.0; n];
+            for i in 0..n {
+                y[i] = pb[(i, c)] - (0..i).map(|j| l[(i, j)] * y[j]).sum::<f64>();
+            }
+            for i in (0..n).rev() {
+                let sum: f64 = (i + 1..n).map(|j| u[(i, j)] * x[(j, c)]).sum();
+                x[(i, c)] = (y[i] - sum) / u[(i, i)];
+            }
+        }
+        Ok(x)
+    }
+
+    pub fn inverse(&self) -> Result<Self, String> {
+        self.require_square("the inverse")?;
+        self.solve(&Self::identity(self.rows))
+    }
+
+    pub fn rank(&self) -> usize {
+        let tolerance = self.tolerance();
+        let mut m = self.clone();
+        let mut rank = 0;
+        for col in 0..m.cols {
+            if rank == m.rows {
+                break;
+            }
+            let pivot = (rank..m.rows)
+                .max_by(|a, b| m[(*a, col)].abs().total_cmp(&m[(*b, col)].abs()))
+                .unwrap();
+            if m[(pivot, col)].abs() <= tolerance {
+                continue;
+            }
+            m.swap_rows(rank, pivot);
+            for i in rank + 1..m.rows {
+                let factor = m[(i, col)] / m[(rank, col)];
+                for j in col..m.cols {
+                    m[(i, j)] -= factor * m[(rank, j)];
+                }
+            }
+            rank += 1;
+        }
+        rank
+    }
+
+    /// QR decomposition with Householder reflections, returning `(Q, R)` where `A = QR`
+    pub fn qr(&self) -> (Self, Self) {
+        let (m, n) = (self.rows, self.cols);
+        let tolerance = self.tolerance();
+        let mut q = Self::identity(m);
+        let mut r = self.clone();
+
+        for k in 0..n.min(m.saturating_sub(1)) {
+            let norm = (k..m).map(|i| r[(i, k)].powi(2)).sum::<f64>().sqrt();
+            if norm <= tolerance {
+                continue;
+            }
+
+            let alpha = if r[(k, k)] > 0.0 { -norm } else { norm };
+            let mut v = vec![0.0; m];
+            v[k] = r[(k, k)] - alpha;
+            for i in k + 1..m {
+                v[i] = r[(i, k)];
+            }
+            let v_norm = v.iter().map(|x| x * x).sum::<f64>();
+            if v_norm <= tolerance * tolerance {
+                continue;
+            }
+
+            // Apply H = I - 2vvᵀ/vᵀv to R from the left and to Q from the right
+            for j in 0..n {
+                let dot: f64 = (k..m).map(|i| v[i] * r[(i, j)]).sum();
+                for i in k..m {
+                    r[(i, j)] -= 2.0 * v[i] * dot / v_norm;
+                }
+            }
+            for i in 0..m {
+                let dot: f64 = (k..m).map(|j| q[(i, j)] * v[j]).sum();
+                for j in k..m {
+                    q[(i, j)] -= 2.0 * dot * v[j] / v_norm;
+                }
+            }
+        }
+
+        (q, r)
+    }
+
+    /// Eigenvalues and eigenvectors of a symmetric matrix with the Jacobi method, returning the
+    /// eigenvalues in ascending order and the matching eigenvectors as columns
+    pub fn eig_symmetric(&self) -> Result<(Vec<f64>, Self), String> {
+        self.require_square("eigendecomposition")?;
+        if *self != self.transpose() {
+            return Err("eigendecomposition is only supported for symmetric matrices".to_string());
+        }
+
+        let n = self.rows;
+        let tolerance = self.tolerance();
+        let mut a = self.clone();
+        let mut v = Self::identity(n);
+
+        for _ in 0..100 {
+            let off_diagonal: f64 = (0..n)
+                .flat_map(|i| (0..n).filter(move |j| *j != i).map(move |j| (i, j)))
+                .map(|(i, j)| a[(i, j)].powi(2))
+                .sum();
+            if off_diagonal <= tolerance * tolerance {
+                break;
+            }
+
+            for p in 0..n {
+                for q in p + 1..n {
+                    if a[(p, q)].abs() < f64::MIN_POSITIVE {
+                        continue;
+                    }
+
+                    let theta = (a[(q, q)] - a[(p, p)]) / (2.0 * a[(p, q)]);
+                    let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                    let c = 1.0 / (t * t + 1.0).sqrt();
+                    let s = t * c;
+
+                    for k in 0..n {
+                        let (akp, akq) = (a[(k, p)], a[(k, q)]);
+                        a[(k, p)] = c * akp - s * akq;
+                        a[(k, q)] = s * akp + c * akq;
+                    }
+                    for k in 0..n {
+                        let (apk, aqk) = (a[(p, k)], a[(q, k)]);
+                        a[(p, k)] = c * apk - s * aqk;
+                        a[(q, k)] = s * apk + c * aqk;
+                    }
+                    for k in 0..n {
+                        let (vkp, vkq) = (v[(k, p)], v[(k, q)]);
+                        v[(k, p)] = c * vkp - s * vkq;
+                        v[(k, q)] = s * vkp + c * vkq;
+                    }
+                }
+            }
+        }
+
+        let mut order: Vec<usize> = (0..n).collect();
+        order.sort_by(|i, j| a[(*i, *i)].total_cmp(&a[(*j, *j)]));
+
+        let values = order.iter().map(|i| a[(*i, *i)]).collect();
+        let mut vectors = Self::zeros(n, n);
+        for (col, i) in order.iter().enumerate() {
+            for k in 0..n {
+                vectors[(k, col)] = v[(k, *i)];
+            }
+        }
+        Ok((values, vectors))
+    }
+
+    fn swap_rows(&mut self, a: usize, b: usize) {
+        for j in 0..self.cols {
+            self.data.swap(a * self.cols + j, b * self.cols + j);
+        }
+    }
+}
+
+impl std::ops::Index<(usize, usize)> for Matrix {
+    type Output = f64;
+
+    fn index(&self, (i, j): (usize, usize)) -> &f64 {
+        &self.data[i * self.cols + j]
+    }
+}
+
+impl std::ops::IndexMut<(usize, usize)> for Matrix {
+    fn index_mut(&mut self, (i, j): (usize, usize)) -> &mut f64 {
+        &mut self.data[i * self.cols + j]
+    }
+}
+
+impl fmt::Display for Matrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+        for i in 0..self.rows {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            for j in 0..self.cols {
+                if j > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", self[(i, j)])?;
+            }
+        }
+        write!(f, "]")
+    }
+}
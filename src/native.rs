@@ -0,0 +1,149 @@
+use std::{
+    fmt,
+    ops::{Deref, Range, RangeInclusive},
+    rc::Rc,
+};
+
+use crate::{AmiError, Interpreter, Value};
+
+type NativeResult = Result<Value, NativeError>;
+type Builtin = dyn Fn(&mut Interpreter, &Args) -> NativeResult;
+
+/// A built-in function. It gets the interpreter, so it can call functions it's given, and
+/// its arguments along with where they are in the source.
+#[derive(Clone)]
+pub struct NativeFunction {
+    pub name: Rc<str>,
+    /// How many arguments it takes, which is checked before it's called
+    pub arity: RangeInclusive<usize>,
+    function: Rc<Builtin>,
+}
+
+impl NativeFunction {
+    pub fn new(
+        name: &str,
+        arity: RangeInclusive<usize>,
+        function: impl Fn(&mut Interpreter, &Args) -> NativeResult + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            arity,
+            function: Rc::new(function),
+        }
+    }
+
+    pub fn call(&self, interpreter: &mut Interpreter, args: &Args) -> NativeResult {
+        if !self.arity.contains(&args.len()) {
+            let (min, max) = (*self.arity.start(), *self.arity.end());
+            let expected = if min == max {
+                plural(min, "argument")
+            } else if max == usize::MAX {
+                format!("at least {}", plural(min, "argument"))
+            } else {
+                format!("{} to {} arguments", min, max)
+            };
+            return Err(NativeError::Call(format!(
+                "{} takes {}, but was given {}",
+                self.name,
+                expected,
+                args.len()
+            )));
+        }
+        (self.function)(interpreter, args)
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NativeFunction({})", self.name)
+    }
+}
+
+/// The arguments of a call to a built-in
+pub struct Args<'a> {
+    values: &'a [Value],
+    ranges: &'a [Range<usize>],
+    /// The whole call, for errors that aren't about one argument
+    pub range: Range<usize>,
+}
+
+impl<'a> Args<'a> {
+    /// `ranges` can be left empty when the arguments don't come from the source, like when a
+    /// built-in calls another, and errors will point at the whole call instead
+    pub fn new(values: &'a [Value], ranges: &'a [Range<usize>], range: Range<usize>) -> Self {
+        Self {
+            values,
+            ranges,
+            range,
+        }
+    }
+
+    /// Where argument `index` is in the source
+    pub fn range(&self, index: usize) -> Range<usize> {
+        self.ranges
+            .get(index)
+            .cloned()
+            .unwrap_or_else(|| self.range.clone())
+    }
+
+    /// An error pointing at argument `index`
+    pub fn error(&self, index: usize, reason: String) -> NativeError {
+        NativeError::Argument(self.range(index), reason)
+    }
+}
+
+impl Deref for Args<'_> {
+    type Target = [Value];
+
+    fn deref(&self) -> &[Value] {
+        self.values
+    }
+}
+
+pub enum NativeError {
+    /// Something wrong with the call as a whole, like a matrix that can't be inverted
+    Call(String),
+    /// Something wrong with one of the arguments
+    Argument(Range<usize>, String),
+    /// An error from a function the built-in called, which already points somewhere
+    Runtime(AmiError),
+}
+
+impl NativeError {
+    /// The error to report for a call to the built-in `name` at `range`
+    pub fn into_error(self, name: &str, range: Range<usize>) -> AmiError {
+        match self {
+            Self::Call(reason) => AmiError {
+                msg: format!("invalid call to {}", name),
+                reason,
+                range,
+            },
+            Self::Argument(range, reason) => AmiError {
+                msg: format!("invalid argument to {}", name),
+                reason,
+                range,
+            },
+            Self::Runtime(error) => error,
+        }
+    }
+}
+
+impl From<String> for NativeError {
+    fn from(reason: String) -> Self {
+        Self::Call(reason)
+    }
+}
+
+impl From<AmiError> for NativeError {
+    fn from(error: AmiError) -> Self {
+        Self::Runtime(error)
+    }
+}
+
+pub(crate) fn plural(n: usize, word: &str) -> String {
+    if n == 1 {
+        format!("{} {}", n, word)
+    } else {
+        format!("{} {}s", n, word)
+    }
+}
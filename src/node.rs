@@ -13,15 +13,28 @@ pub enum UnaryOp {
     Fort,
     Degree,
     Fact,
+    DoubleFact,
+    Not,
 }
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum BinaryOp {
     Add,
     Sub,
     Mul,
+    Dot,
+    Cross,
     Div,
     Mod,
     Pow,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+    Compose,
 }
 
 impl fmt::Display for BinaryOp {
@@ -30,30 +43,208 @@ impl fmt::Display for BinaryOp {
             Self::Add => write!(f, "+"),
             Self::Sub => write!(f, "-"),
             Self::Mul => write!(f, "×"),
+            Self::Dot => write!(f, "∙"),
+            Self::Cross => write!(f, "×"),
             Self::Div => write!(f, "÷"),
             Self::Mod => write!(f, "mod"),
             Self::Pow => write!(f, "^"),
+            Self::Eq => write!(f, "="),
+            Self::Ne => write!(f, "≠"),
+            Self::Lt => write!(f, "<"),
+            Self::Le => write!(f, "≤"),
+            Self::Gt => write!(f, ">"),
+            Self::Ge => write!(f, "≥"),
+            Self::And => write!(f, "∧"),
+            Self::Or => write!(f, "∨"),
+            Self::Compose => write!(f, "∘"),
         }
     }
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum NodeType {
     Number(Rc<str>),
+    Bool(bool),
     Identifier(Rc<str>),
     Assignment(Rc<str>, Box<Node>),
     Unary(UnaryOp, Box<Node>),
     Binary(Box<Node>, BinaryOp, Box<Node>),
     FnDef(Rc<str>, Vec<Rc<str>>, Box<Node>),
+    Lambda(Vec<Rc<str>>, Box<Node>),
     Call(Rc<str>, Vec<Node>),
+    /// A call of something other than a name, like `(f ∘ g)(x)`
+    Apply(Box<Node>, Vec<Node>),
+    Vector(Vec<Node>),
+    Matrix(Vec<Vec<Node>>),
+    Index(Box<Node>, Vec<Node>),
+    If(Box<Node>, Box<Node>, Option<Box<Node>>),
+    Sum(Rc<str>, Box<Node>, Box<Node>, Box<Node>),
+    Product(Rc<str>, Box<Node>, Box<Node>, Box<Node>),
+    Integral(Rc<str>, Box<Node>, Box<Node>, Box<Node>),
+    Derivative(Box<Node>, Rc<str>),
+    Solve(Box<Node>, Rc<str>),
+    /// Statements in braces, evaluated in their own scope
+    Block(Vec<Node>),
     Statements(Vec<Node>),
     EOF,
 }
 
+impl NodeType {
+    /// How tightly the expression binds, following the levels of the parser's grammar
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Assignment(..)
+            | Self::FnDef(..)
+            | Self::Lambda(..)
+            | Self::If(..)
+            | Self::Statements(_) => 1,
+            Self::Binary(_, op, _) => match op {
+                BinaryOp::Or => 2,
+                BinaryOp::And => 3,
+                BinaryOp::Eq
+                | BinaryOp::Ne
+                | BinaryOp::Lt
+                | BinaryOp::Le
+                | BinaryOp::Gt
+                | BinaryOp::Ge => 5,
+                BinaryOp::Add | BinaryOp::Sub => 6,
+                BinaryOp::Mul if self.is_implicit() => 9,
+                BinaryOp::Mul
+                | BinaryOp::Dot
+                | BinaryOp::Cross
+                | BinaryOp::Div
+                | BinaryOp::Mod
+                | BinaryOp::Compose => 7,
+                BinaryOp::Pow => 10,
+            },
+            Self::Unary(op, _) => match op {
+                UnaryOp::Not => 4,
+                UnaryOp::Pos | UnaryOp::Neg => 8,
+                UnaryOp::Sqrt | UnaryOp::Cbrt | UnaryOp::Fort => 11,
+                UnaryOp::Degree | UnaryOp::Fact | UnaryOp::DoubleFact => 12,
+                UnaryOp::Abs | UnaryOp::Floor | UnaryOp::Ceil | UnaryOp::Round => 13,
+            },
+            Self::Index(..) => 12,
+            _ => 13,
+        }
+    }
+
+    /// Whether a product can be written without the sign, like `2x` or `3sin(x)`
+    fn is_implicit(&self) -> bool {
+        let Self::Binary(left, BinaryOp::Mul, right) = self else {
+            return false;
+        };
+        let named = |node: &Node| matches!(node.ty, Self::Identifier(_) | Self::Call(..));
+        matches!(left.ty, Self::Number(_))
+            && (named(right)
+                || matches!(&right.ty, Self::Binary(base, BinaryOp::Pow, _) if named(base)))
+    }
+
+    /// Writes the expression with only the parentheses it needs, like `2x^2 + 1`
+    fn pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let wrap = |node: &Node, min: u8| {
+            if node.ty.precedence() < min {
+                format!("({:#})", node)
+            } else {
+                format!("{:#}", node)
+            }
+        };
+        let list = |nodes: &[Node]| {
+            nodes
+                .iter()
+                .map(|node| format!("{:#}", node))
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+
+        match self {
+            Self::Assignment(name, node) => write!(f, "{} = {:#}", name, node),
+            Self::Unary(op, node) => match op {
+                UnaryOp::Pos => write!(f, "+{}", wrap(node, 8)),
+                UnaryOp::Neg => write!(f, "-{}", wrap(node, 8)),
+                UnaryOp::Abs => write!(f, "|{:#}|", node),
+                UnaryOp::Floor => write!(f, "⌊{:#}⌋", node),
+                UnaryOp::Ceil => write!(f, "⌈{:#}⌉", node),
+                UnaryOp::Round => write!(f, "⌊{:#}⌉", node),
+                UnaryOp::Sqrt => write!(f, "√{}", wrap(node, 12)),
+                UnaryOp::Cbrt => write!(f, "∛{}", wrap(node, 12)),
+                UnaryOp::Fort => write!(f, "∜{}", wrap(node, 12)),
+                UnaryOp::Degree => write!(f, "{}°", wrap(node, 13)),
+                UnaryOp::Fact => write!(f, "{}!", wrap(node, 13)),
+                UnaryOp::DoubleFact => write!(f, "{}!!", wrap(node, 13)),
+                UnaryOp::Not => write!(f, "¬{}", wrap(node, 4)),
+            },
+            Self::Binary(left, BinaryOp::Pow, right) => {
+                write!(f, "{}^{}", wrap(left, 11), wrap(right, 10))
+            }
+            Self::Binary(left, _, right) if self.is_implicit() => {
+                write!(f, "{:#}{}", left, wrap(right, 10))
+            }
+            Self::Binary(left, op, right) => {
+                // Comparisons chain, so a nested one on the left needs parentheses too
+                let precedence = self.precedence();
+                let left_min = if precedence == 5 { 6 } else { precedence };
+                write!(
+                    f,
+                    "{} {} {}",
+                    wrap(left, left_min),
+                    op,
+                    wrap(right, precedence + 1)
+                )
+            }
+            Self::Lambda(args, body) => match args.as_slice() {
+                [arg] => write!(f, "{} ↦ {:#}", arg, body),
+                _ => write!(f, "({}) ↦ {:#}", args.join(", "), body),
+            },
+            Self::Call(name, args) => write!(f, "{}({})", name, list(args)),
+            Self::Apply(callee, args) => write!(f, "{}({})", wrap(callee, 12), list(args)),
+            Self::Vector(nodes) => write!(f, "[{}]", list(nodes)),
+            Self::Matrix(rows) => write!(
+                f,
+                "[{}]",
+                rows.iter()
+                    .map(|row| list(row))
+                    .collect::<Vec<String>>()
+                    .join("; ")
+            ),
+            Self::Index(node, indices) => write!(f, "{}[{}]", wrap(node, 12), list(indices)),
+            Self::If(condition, then, otherwise) => match otherwise {
+                Some(otherwise) => write!(
+                    f,
+                    "if {} then {} else {:#}",
+                    wrap(condition, 2),
+                    wrap(then, 2),
+                    otherwise
+                ),
+                None => write!(f, "{{ {:#} if {:#} }}", then, condition),
+            },
+            Self::Sum(index, lower, upper, body) => {
+                write!(f, "Σ({} = {:#}, {:#}, {:#})", index, lower, upper, body)
+            }
+            Self::Product(index, lower, upper, body) => {
+                write!(f, "Π({} = {:#}, {:#}, {:#})", index, lower, upper, body)
+            }
+            Self::Integral(var, lower, upper, body) => {
+                write!(f, "∫({:#}, {:#}, {:#}, {})", lower, upper, body, var)
+            }
+            Self::Derivative(node, var) => write!(f, "d/d{} {}", var, wrap(node, 8)),
+            Self::Solve(equation, var) => write!(f, "solve({:#}, {})", equation, var),
+            _ => write!(f, "{}", self),
+        }
+    }
+}
+
+/// Shows the tree fully parenthesized, or as readable math with the alternate flag (`{:#}`)
 impl fmt::Display for NodeType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return self.pretty(f);
+        }
+
         match self {
             Self::Number(x) => write!(f, "{}", x),
+            Self::Bool(value) => write!(f, "{}", value),
             Self::Identifier(name) => write!(f, "{}", name),
             Self::Assignment(name, node) => write!(f, "({} = {})", name, node),
             Self::Unary(op, node) => match op {
@@ -68,6 +259,8 @@ impl fmt::Display for NodeType {
                 UnaryOp::Fort => write!(f, "(∜{})", node),
                 UnaryOp::Degree => write!(f, "({}°)", node),
                 UnaryOp::Fact => write!(f, "({}!)", node),
+                UnaryOp::DoubleFact => write!(f, "({}!!)", node),
+                UnaryOp::Not => write!(f, "(¬{})", node),
             },
             Self::Binary(left, op, right) => write!(f, "({} {} {})", left, op, right),
             Self::FnDef(name, args, body) => write!(
@@ -80,6 +273,10 @@ impl fmt::Display for NodeType {
                     .join(", "),
                 body
             ),
+            Self::Lambda(args, body) => match args.as_slice() {
+                [arg] => write!(f, "({} ↦ {})", arg, body),
+                _ => write!(f, "(({}) ↦ {})", args.join(", "), body),
+            },
             Self::Call(name, args) => write!(
                 f,
                 "{}({})",
@@ -89,6 +286,72 @@ impl fmt::Display for NodeType {
                     .collect::<Vec<String>>()
                     .join(", ")
             ),
+            Self::Apply(callee, args) => write!(
+                f,
+                "{}({})",
+                callee,
+                args.iter()
+                    .map(|arg| format!("{}", arg))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Self::Vector(nodes) => write!(
+                f,
+                "[{}]",
+                nodes
+                    .iter()
+                    .map(|node| format!("{}", node))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Self::Matrix(rows) => write!(
+                f,
+                "[{}]",
+                rows.iter()
+                    .map(|row| row
+                        .iter()
+                        .map(|node| format!("{}", node))
+                        .collect::<Vec<String>>()
+                        .join(", "))
+                    .collect::<Vec<String>>()
+                    .join("; ")
+            ),
+            Self::Index(node, indices) => write!(
+                f,
+                "{}[{}]",
+                node,
+                indices
+                    .iter()
+                    .map(|index| format!("{}", index))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Self::If(condition, then, otherwise) => match otherwise {
+                Some(otherwise) => {
+                    write!(f, "(if {} then {} else {})", condition, then, otherwise)
+                }
+                None => write!(f, "(if {} then {})", condition, then),
+            },
+            Self::Sum(index, lower, upper, body) => {
+                write!(f, "Σ({} = {}, {}, {})", index, lower, upper, body)
+            }
+            Self::Product(index, lower, upper, body) => {
+                write!(f, "Π({} = {}, {}, {})", index, lower, upper, body)
+            }
+            Self::Integral(var, lower, upper, body) => {
+                write!(f, "∫({}, {}, {}, {})", lower, upper, body, var)
+            }
+            Self::Derivative(node, var) => write!(f, "(d/d{} {})", var, node),
+            Self::Solve(equation, var) => write!(f, "solve({}, {})", equation, var),
+            Self::Block(nodes) => write!(
+                f,
+                "{{ {} }}",
+                nodes
+                    .iter()
+                    .map(|node| node.to_string())
+                    .collect::<Vec<String>>()
+                    .join("; ")
+            ),
             Self::Statements(nodes) => write!(
                 f,
                 "{{\n  {}\n}}",
@@ -109,8 +372,79 @@ pub struct Node {
     pub range: Range<usize>,
 }
 
+impl Node {
+    /// Rebuilds the node with `f` applied to each of its direct children
+    pub fn map_children(&self, mut f: impl FnMut(&Node) -> Node) -> Node {
+        let mut child = |node: &Node| Box::new(f(node));
+        let ty = match &self.ty {
+            NodeType::Assignment(name, node) => NodeType::Assignment(name.clone(), child(node)),
+            NodeType::Unary(op, node) => NodeType::Unary(*op, child(node)),
+            NodeType::Binary(left, op, right) => NodeType::Binary(child(left), *op, child(right)),
+            NodeType::FnDef(name, args, body) => {
+                NodeType::FnDef(name.clone(), args.clone(), child(body))
+            }
+            NodeType::Lambda(args, body) => NodeType::Lambda(args.clone(), child(body)),
+            NodeType::Call(name, args) => {
+                NodeType::Call(name.clone(), args.iter().map(|arg| *child(arg)).collect())
+            }
+            NodeType::Apply(callee, args) => {
+                NodeType::Apply(child(callee), args.iter().map(|arg| *child(arg)).collect())
+            }
+            NodeType::Vector(nodes) => {
+                NodeType::Vector(nodes.iter().map(|node| *child(node)).collect())
+            }
+            NodeType::Matrix(rows) => NodeType::Matrix(
+                rows.iter()
+                    .map(|row| row.iter().map(|node| *child(node)).collect())
+                    .collect(),
+            ),
+            NodeType::Index(node, indices) => NodeType::Index(
+                child(node),
+                indices.iter().map(|index| *child(index)).collect(),
+            ),
+            NodeType::If(condition, then, otherwise) => NodeType::If(
+                child(condition),
+                child(then),
+                otherwise.as_ref().map(|otherwise| child(otherwise)),
+            ),
+            NodeType::Sum(index, lower, upper, body) => {
+                NodeType::Sum(index.clone(), child(lower), child(upper), child(body))
+            }
+            NodeType::Product(index, lower, upper, body) => {
+                NodeType::Product(index.clone(), child(lower), child(upper), child(body))
+            }
+            NodeType::Integral(var, lower, upper, body) => {
+                NodeType::Integral(var.clone(), child(lower), child(upper), child(body))
+            }
+            NodeType::Derivative(node, var) => NodeType::Derivative(child(node), var.clone()),
+            NodeType::Solve(equation, var) => NodeType::Solve(child(equation), var.clone()),
+            NodeType::Block(nodes) => {
+                NodeType::Block(nodes.iter().map(|node| *child(node)).collect())
+            }
+            NodeType::Statements(nodes) => {
+                NodeType::Statements(nodes.iter().map(|node| *child(node)).collect())
+            }
+            ty @ (NodeType::Number(_)
+            | NodeType::Bool(_)
+            | NodeType::Identifier(_)
+            | NodeType::EOF) => ty.clone(),
+        };
+        Node {
+            ty,
+            range: self.range.clone(),
+        }
+    }
+}
+
+/// Nodes built by the interpreter rather than parsed from the source don't have a range
+impl From<NodeType> for Node {
+    fn from(ty: NodeType) -> Self {
+        Self { ty, range: 0..0 }
+    }
+}
+
 impl fmt::Display for Node {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.ty)
+        self.ty.fmt(f)
     }
 }
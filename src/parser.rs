@@ -1,45 +1,55 @@
 use crate::{AmiError, BinaryOp, Node, NodeType, Token, TokenType, UnaryOp};
-use std::{iter::Peekable, rc::Rc, vec::IntoIter};
+use std::{rc::Rc, vec::IntoIter};
 
 use TokenType::*;
 
 pub struct Parser {
-    tokens: Peekable<IntoIter<Token>>,
+    /// The tokens after `token`
+    tokens: IntoIter<Token>,
     token: Token,
+    /// Where the last consumed token ended, so node ranges don't spill into the next token
+    last_end: usize,
+    /// Set while parsing an integrand, so `2 dx` isn't read as `2 × dx`
+    in_integral: bool,
 }
 
 type ParseResult = Result<Node, AmiError>;
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        let mut iter = tokens.into_iter().peekable();
+        let mut iter = tokens.into_iter();
         Self {
-            token: iter.next().unwrap_or(Token {
-                ty: EOF,
-                range: Default::default(),
-            }),
+            token: iter.next().unwrap_or(Token::new(EOF, Default::default())),
             tokens: iter,
+            last_end: 0,
+            in_integral: false,
         }
     }
 
-    fn peek(&mut self) -> &TokenType {
-        match self.tokens.peek() {
+    fn peek(&self) -> &TokenType {
+        self.lookahead(0)
+    }
+
+    /// The type of a token further ahead, where `lookahead(0)` is the one right after `token`
+    fn lookahead(&self, n: usize) -> &TokenType {
+        match self.tokens.as_slice().get(n) {
             Some(token) => &token.ty,
             None => &EOF,
         }
     }
 
     fn advance(&mut self) {
-        self.token = self.tokens.next().unwrap_or(Token {
-            ty: EOF,
-            range: Default::default(),
-        });
+        self.last_end = self.token.range.end;
+        self.token = self
+            .tokens
+            .next()
+            .unwrap_or(Token::new(EOF, self.last_end..self.last_end));
     }
 
     fn node(&self, ty: NodeType, start: usize) -> ParseResult {
         Ok(Node {
             ty,
-            range: start..self.token.range.end,
+            range: start..self.last_end,
         })
     }
 
@@ -51,6 +61,18 @@ impl Parser {
         })
     }
 
+    fn expect(&mut self, ty: TokenType, start: usize) -> Result<(), AmiError> {
+        if self.token.ty != ty {
+            return self.error(
+                "expected token".to_string(),
+                format!("expected {}", ty),
+                start,
+            );
+        }
+        self.advance();
+        Ok(())
+    }
+
     fn skip_newlines(&mut self) -> u32 {
         let mut newlines = 0u32;
         while self.token.ty == Newline {
@@ -60,6 +82,16 @@ impl Parser {
         newlines
     }
 
+    /// Skips the semicolons and newlines between statements, returning how many there were
+    fn skip_separators(&mut self) -> u32 {
+        let mut separators = 0u32;
+        while matches!(self.token.ty, Semicolon | Newline) {
+            self.advance();
+            separators += 1;
+        }
+        separators
+    }
+
     pub fn parse(&mut self) -> ParseResult {
         self.statements()
     }
@@ -67,28 +99,23 @@ impl Parser {
     fn statements(&mut self) -> ParseResult {
         let start = self.token.range.start;
         let mut statements: Vec<Node> = vec![];
-        self.skip_newlines();
+        self.skip_separators();
 
         statements.push(self.statement()?);
-
-        let mut more_statements = true;
-
-        loop {
-            let newlines = self.skip_newlines();
-            if newlines == 0 {
-                more_statements = false;
+        while self.token.ty != EOF {
+            if self.skip_separators() == 0 {
+                return self.error(
+                    "expected token".to_string(),
+                    format!(
+                        "expected {}, {}, or the end of the input",
+                        Semicolon, Newline
+                    ),
+                    self.token.range.start,
+                );
             }
-
-            if !more_statements {
-                break;
+            if self.token.ty != EOF {
+                statements.push(self.statement()?);
             }
-
-            let statement = self.statement()?;
-            if statement.ty == NodeType::EOF {
-                more_statements = false;
-                continue;
-            }
-            statements.push(statement);
         }
 
         self.node(NodeType::Statements(statements), start)
@@ -105,102 +132,193 @@ impl Parser {
             (Identifier(name), Eq) => {
                 self.advance();
                 self.advance();
-                let right = self.arith_expr()?;
+                let right = self.cond_expr()?;
                 self.node(NodeType::Assignment(name, Box::new(right)), start)
             }
-            _ => self.arith_expr(),
+            _ => self.cond_expr(),
         }
     }
 
-    fn arith_expr(&mut self) -> ParseResult {
+    fn cond_expr(&mut self) -> ParseResult {
         let start = self.token.range.start;
-        let left = self.term()?;
 
-        match self.token.ty {
-            Plus => {
+        if let Some(arg_names) = self.lambda_args() {
+            while self.token.ty != Arrow {
                 self.advance();
-                let right = self.arith_expr()?;
-                self.node(
-                    NodeType::Binary(Box::new(left), BinaryOp::Add, Box::new(right)),
-                    start,
-                )
             }
-            Minus => {
-                self.advance();
-                let right = self.arith_expr()?;
-                self.node(
-                    NodeType::Binary(Box::new(left), BinaryOp::Sub, Box::new(right)),
-                    start,
-                )
+            self.advance();
+            let body = self.cond_expr()?;
+            return self.node(NodeType::Lambda(arg_names, Box::new(body)), start);
+        }
+
+        if self.token.ty != If {
+            return self.or_expr();
+        }
+        self.advance();
+
+        let condition = self.or_expr()?;
+        self.expect(Then, start)?;
+        let then = self.cond_expr()?;
+        self.expect(Else, start)?;
+        let otherwise = self.cond_expr()?;
+
+        self.node(
+            NodeType::If(
+                Box::new(condition),
+                Box::new(then),
+                Some(Box::new(otherwise)),
+            ),
+            start,
+        )
+    }
+
+    /// The arguments of a lambda starting here, like `x ↦` or `(x, y) ->`. Looks ahead for the
+    /// arrow, so a parenthesized expression isn't mistaken for one.
+    fn lambda_args(&mut self) -> Option<Vec<Rc<str>>> {
+        match self.token.ty.clone() {
+            Identifier(name) if *self.peek() == Arrow => Some(vec![name]),
+            LeftParen if matches!(self.peek(), Identifier(_) | RightParen) => {
+                let mut tokens = self.tokens.as_slice().iter().map(|token| token.ty.clone());
+                let mut arg_names: Vec<Rc<str>> = vec![];
+                loop {
+                    match tokens.next()? {
+                        Identifier(name) => arg_names.push(name),
+                        RightParen if arg_names.is_empty() => break,
+                        _ => return None,
+                    }
+                    match tokens.next()? {
+                        Comma => {}
+                        RightParen => break,
+                        _ => return None,
+                    }
+                }
+                (tokens.next()? == Arrow).then_some(arg_names)
             }
-            _ => Ok(left),
+            _ => None,
         }
     }
 
-    fn term(&mut self) -> ParseResult {
+    fn or_expr(&mut self) -> ParseResult {
         let start = self.token.range.start;
+        let mut left = self.and_expr()?;
 
-        if matches!(self.token.ty, Number(_))
-            && !matches!(
-                self.peek(),
-                Number(_)
-                    | Superscript(_)
-                    | Plus
-                    | Minus
-                    | Star
-                    | Dot
-                    | Cross
-                    | Slash
-                    | Divide
-                    | Percent
-                    | Carrot
-                    | RightParen
-                    | LeftBrace
-                    | RightBrace
-                    | Pipe
-                    | RightFloor
-                    | RightCeil
-                    | Newline
-                    | EOF
-            )
-        {
-            let left = self.atom()?;
-            let right = self.term()?;
-            return self.node(
-                NodeType::Binary(Box::new(left), BinaryOp::Mul, Box::new(right)),
+        while self.token.ty == Or {
+            self.advance();
+            let right = self.and_expr()?;
+            left = self.node(
+                NodeType::Binary(Box::new(left), BinaryOp::Or, Box::new(right)),
                 start,
-            );
+            )?;
         }
 
-        let left = self.factor()?;
+        Ok(left)
+    }
+
+    fn and_expr(&mut self) -> ParseResult {
+        let start = self.token.range.start;
+        let mut left = self.not_expr()?;
+
+        while self.token.ty == And {
+            self.advance();
+            let right = self.not_expr()?;
+            left = self.node(
+                NodeType::Binary(Box::new(left), BinaryOp::And, Box::new(right)),
+                start,
+            )?;
+        }
+
+        Ok(left)
+    }
+
+    fn not_expr(&mut self) -> ParseResult {
+        let start = self.token.range.start;
 
         match self.token.ty {
-            Star | Dot | Cross => {
+            Not => {
                 self.advance();
-                let right = self.term()?;
-                self.node(
-                    NodeType::Binary(Box::new(left), BinaryOp::Mul, Box::new(right)),
-                    start,
-                )
+                let right = self.not_expr()?;
+                self.node(NodeType::Unary(UnaryOp::Not, Box::new(right)), start)
             }
-            Slash | Divide => {
-                self.advance();
-                let right = self.term()?;
-                self.node(
-                    NodeType::Binary(Box::new(left), BinaryOp::Div, Box::new(right)),
-                    start,
-                )
-            }
-            Percent | Mod => {
-                self.advance();
-                let right = self.term()?;
-                self.node(
-                    NodeType::Binary(Box::new(left), BinaryOp::Mod, Box::new(right)),
+            _ => self.comp_expr(),
+        }
+    }
+
+    /// Comparisons can be chained like in math, so `a < b ≤ c` means `a < b ∧ b ≤ c`
+    fn comp_expr(&mut self) -> ParseResult {
+        let start = self.token.range.start;
+        let mut left = self.arith_expr()?;
+        let mut result: Option<Node> = None;
+
+        loop {
+            let op = match self.token.ty {
+                Eq => BinaryOp::Eq,
+                Ne => BinaryOp::Ne,
+                Lt => BinaryOp::Lt,
+                Le => BinaryOp::Le,
+                Gt => BinaryOp::Gt,
+                Ge => BinaryOp::Ge,
+                _ => break,
+            };
+            self.advance();
+
+            let right = self.arith_expr()?;
+            let comparison = self.node(
+                NodeType::Binary(Box::new(left), op, Box::new(right.clone())),
+                start,
+            )?;
+            result = Some(match result {
+                Some(prev) => self.node(
+                    NodeType::Binary(Box::new(prev), BinaryOp::And, Box::new(comparison)),
                     start,
-                )
-            }
-            _ => Ok(left),
+                )?,
+                None => comparison,
+            });
+            left = right;
+        }
+
+        Ok(result.unwrap_or(left))
+    }
+
+    fn arith_expr(&mut self) -> ParseResult {
+        let start = self.token.range.start;
+        let mut left = self.term()?;
+
+        loop {
+            let op = match self.token.ty {
+                Plus => BinaryOp::Add,
+                Minus => BinaryOp::Sub,
+                _ => break,
+            };
+            self.advance();
+
+            let right = self.term()?;
+            left = self.node(NodeType::Binary(Box::new(left), op, Box::new(right)), start)?;
         }
+
+        Ok(left)
+    }
+
+    fn term(&mut self) -> ParseResult {
+        let start = self.token.range.start;
+        let mut left = self.factor()?;
+
+        loop {
+            let op = match self.token.ty {
+                Star => BinaryOp::Mul,
+                Dot => BinaryOp::Dot,
+                Cross => BinaryOp::Cross,
+                Slash | Divide => BinaryOp::Div,
+                Percent | Mod => BinaryOp::Mod,
+                Compose => BinaryOp::Compose,
+                _ => break,
+            };
+            self.advance();
+
+            let right = self.factor()?;
+            left = self.node(NodeType::Binary(Box::new(left), op, Box::new(right)), start)?;
+        }
+
+        Ok(left)
     }
 
     fn factor(&mut self) -> ParseResult {
@@ -217,10 +335,46 @@ impl Parser {
                 let right = self.factor()?;
                 self.node(NodeType::Unary(UnaryOp::Neg, Box::new(right)), start)
             }
-            _ => self.power(),
+            _ => self.implicit_mul(),
         }
     }
 
+    /// A number directly followed by a variable, call, root, or group is multiplied
+    /// with it, and binds tighter than explicit operators, so `1/2x` is `1/(2x)`
+    fn implicit_mul(&mut self) -> ParseResult {
+        let start = self.token.range.start;
+
+        let before_differential = self.in_integral
+            && matches!(self.peek(), Identifier(name) if differential(name).is_some());
+        if matches!(self.token.ty, Number(_))
+            && !before_differential
+            && matches!(
+                self.peek(),
+                Identifier(_)
+                    | Sqrt
+                    | Cbrt
+                    | Fort
+                    | LeftParen
+                    | LeftFloor
+                    | LeftCeil
+                    | LeftBracket
+                    | LeftAngle
+                    | Sum
+                    | Product
+                    | Integral
+            )
+        {
+            let left = self.atom()?;
+            let right = self.factor()?;
+            return self.node(
+                NodeType::Binary(Box::new(left), BinaryOp::Mul, Box::new(right)),
+                start,
+            );
+        }
+
+        self.power()
+    }
+
     fn power(&mut self) -> ParseResult {
         let start = self.token.range.start;
         let result = self.prefix()?;
@@ -263,29 +417,52 @@ impl Parser {
 
     fn postfix(&mut self) -> ParseResult {
         let start = self.token.range.start;
-        let result = self.call()?;
+        let mut result = self.call()?;
 
-        match self.token.ty.clone() {
-            Exclamation => {
-                self.advance();
-                self.node(NodeType::Unary(UnaryOp::Fact, Box::new(result)), start)
-            }
-            Degree => {
-                self.advance();
-                self.node(NodeType::Unary(UnaryOp::Degree, Box::new(result)), start)
-            }
-            Superscript(tokens) => {
-                self.advance();
-                self.node(
-                    NodeType::Binary(
-                        Box::new(result),
-                        BinaryOp::Pow,
-                        Box::new(Parser::new(tokens).arith_expr()?),
-                    ),
-                    start,
-                )
-            }
-            _ => Ok(result),
+        loop {
+            result = match self.token.ty.clone() {
+                Exclamation if *self.peek() == Exclamation => {
+                    self.advance();
+                    self.advance();
+                    self.node(
+                        NodeType::Unary(UnaryOp::DoubleFact, Box::new(result)),
+                        start,
+                    )?
+                }
+                Exclamation => {
+                    self.advance();
+                    self.node(NodeType::Unary(UnaryOp::Fact, Box::new(result)), start)?
+                }
+                Degree => {
+                    self.advance();
+                    self.node(NodeType::Unary(UnaryOp::Degree, Box::new(result)), start)?
+                }
+                LeftBracket => {
+                    let list_start = self.token.range.start;
+                    self.advance();
+                    let indices = self.list(list_start, RightBracket)?;
+                    self.node(NodeType::Index(Box::new(result), indices), start)?
+                }
+                // Calls of anything but a name, like (f ∘ g)(x) or adder(2)(3)
+                LeftParen => {
+                    let list_start = self.token.range.start;
+                    self.advance();
+                    let args = self.list(list_start, RightParen)?;
+                    self.node(NodeType::Apply(Box::new(result), args), start)?
+                }
+                Superscript(tokens) => {
+                    self.advance();
+                    self.node(
+                        NodeType::Binary(
+                            Box::new(result),
+                            BinaryOp::Pow,
+                            Box::new(Parser::new(tokens).arith_expr()?),
+                        ),
+                        start,
+                    )?
+                }
+                _ => return Ok(result),
+            };
         }
     }
 
@@ -293,13 +470,10 @@ impl Parser {
         let start = self.token.range.start;
         let result = self.atom()?;
 
-        match self.token.ty {
-            LeftParen => {
+        match (&self.token.ty, &result.ty) {
+            (LeftParen, NodeType::Identifier(name)) => {
                 let list_start = self.token.range.start;
-                let name = match result.ty {
-                    NodeType::Identifier(ref name) => Rc::clone(name),
-                    _ => panic!("expected identifier"),
-                };
+                let name = Rc::clone(name);
                 self.advance();
 
                 let args = self.list(list_start, RightParen)?;
@@ -326,12 +500,76 @@ impl Parser {
                         self.node(NodeType::FnDef(name, arg_names, Box::new(body)), start)
                     }
                     _ => match result.ty {
-                        NodeType::Identifier(name) => self.node(NodeType::Call(name, args), start),
-                        _ => self.error(
-                            "expected token".to_string(),
-                            "there should be an identifier here".to_string(),
-                            start,
-                        ),
+                        // derive(f, x) is syntax, since x names a variable instead of a value
+                        NodeType::Identifier(name)
+                            if &*name == "derive"
+                                && args.len() == 2
+                                && matches!(args[1].ty, NodeType::Identifier(_)) =>
+                        {
+                            let mut args = args.into_iter();
+                            let function = args.next().unwrap();
+                            let NodeType::Identifier(var) = args.next().unwrap().ty else {
+                                unreachable!()
+                            };
+                            self.node(NodeType::Derivative(Box::new(function), var), start)
+                        }
+                        // solve(x² = 2, x) names the unknown, while solve(f, 1) calls the
+                        // built-in with a function and a starting point
+                        NodeType::Identifier(name)
+                            if &*name == "solve"
+                                && args.len() == 2
+                                && matches!(args[1].ty, NodeType::Identifier(_))
+                                && !matches!(args[0].ty, NodeType::Identifier(_)) =>
+                        {
+                            let mut args = args.into_iter();
+                            let equation = args.next().unwrap();
+                            let NodeType::Identifier(var) = args.next().unwrap().ty else {
+                                unreachable!()
+                            };
+                            // Arguments parse `x = 1` as an assignment, but here it's an equation
+                            let equation = match equation.ty {
+                                NodeType::Assignment(name, right) => {
+                                    let start = equation.range.start;
+                                    let name_range = start..start + name.len();
+                                    Node {
+                                        ty: NodeType::Binary(
+                                            Box::new(Node {
+                                                ty: NodeType::Identifier(name),
+                                                range: name_range,
+                                            }),
+                                            BinaryOp::Eq,
+                                            right,
+                                        ),
+                                        range: equation.range,
+                                    }
+                                }
+                                // and f(x) = x is a function definition
+                                NodeType::FnDef(name, arg_names, right) => {
+                                    let left_range = equation.range.start..right.range.start;
+                                    let args = arg_names
+                                        .into_iter()
+                                        .map(|arg| Node {
+                                            ty: NodeType::Identifier(arg),
+                                            range: left_range.clone(),
+                                        })
+                                        .collect();
+                                    Node {
+                                        ty: NodeType::Binary(
+                                            Box::new(Node {
+                                                ty: NodeType::Call(name, args),
+                                                range: left_range,
+                                            }),
+                                            BinaryOp::Eq,
+                                            right,
+                                        ),
+                                        range: equation.range,
+                                    }
+                                }
+                                _ => equation,
+                            };
+                            self.node(NodeType::Solve(Box::new(equation), var), start)
+                        }
+                        _ => self.node(NodeType::Call(name, args), start),
                     },
                 }
             }
@@ -347,13 +585,35 @@ impl Parser {
                 self.advance();
                 self.node(NodeType::Number(x), start)
             }
+            Bool(value) => {
+                self.advance();
+                self.node(NodeType::Bool(value), start)
+            }
+            Identifier(name) if &*name == "d" && *self.peek() == Slash => {
+                // Only `d/dx` followed by something to differentiate is a derivative, so `d/2`
+                // and `d/dt` on their own still divide
+                let var = match self.lookahead(1) {
+                    Identifier(name) if starts_operand(self.lookahead(2)) => differential(name),
+                    _ => None,
+                };
+                let Some(var) = var else {
+                    self.advance();
+                    return self.node(NodeType::Identifier(name), start);
+                };
+                self.advance();
+                self.advance();
+                self.advance();
+                // Like an integrand, the operand runs to the end of the expression
+                let operand = self.arith_expr()?;
+                self.node(NodeType::Derivative(Box::new(operand), var), start)
+            }
             Identifier(name) => {
                 self.advance();
                 self.node(NodeType::Identifier(name), start)
             }
             LeftParen => {
                 self.advance();
-                let result = self.arith_expr()?;
+                let result = self.cond_expr()?;
 
                 if self.token.ty != RightParen {
                     return self.error(
@@ -416,18 +676,311 @@ impl Parser {
 
                 self.node(NodeType::Unary(UnaryOp::Ceil, Box::new(result)), start)
             }
+            LeftBrace => {
+                self.advance();
+                self.braces(start)
+            }
+            LeftBracket => {
+                self.advance();
+                self.brackets(start)
+            }
+            LeftAngle => {
+                self.advance();
+                let nodes = self.list(start, RightAngle)?;
+                self.node(NodeType::Vector(nodes), start)
+            }
+            ty @ (Sum | Product) => {
+                self.advance();
+                self.series(ty, start)
+            }
+            Integral => {
+                self.advance();
+                self.integral(start)
+            }
             EOF => self.node(NodeType::EOF, start),
             _ => self.error(
                 "expected token".to_string(),
                 format!(
-                    "expected number, variable, function name, {}, {}, {}, or {}",
-                    LeftParen, Pipe, LeftFloor, LeftCeil
+                    "expected number, variable, function name, {}, {}, {}, {}, {}, {}, or {}",
+                    LeftParen, Pipe, LeftFloor, LeftCeil, LeftBrace, LeftBracket, LeftAngle
                 ),
                 start,
             ),
         }
     }
 
+    /// Parses the bound index, limits, and body of a sum or product after the sign,
+    /// like `(k = 1, n, k²)`
+    fn series(&mut self, ty: TokenType, start: usize) -> ParseResult {
+        let sign = if ty == Sum { "Σ" } else { "Π" };
+        self.expect(LeftParen, start)?;
+        let Identifier(index) = self.token.ty.clone() else {
+            return self.error(
+                "expected identifier".to_string(),
+                format!("{} needs an index variable, like {}(k = 1, n, k)", ty, sign),
+                start,
+            );
+        };
+        self.advance();
+        self.expect(Eq, start)?;
+        let lower = self.cond_expr()?;
+        self.expect(Comma, start)?;
+        let upper = self.cond_expr()?;
+        self.expect(Comma, start)?;
+        let body = self.cond_expr()?;
+        self.expect(RightParen, start)?;
+
+        let (lower, upper, body) = (Box::new(lower), Box::new(upper), Box::new(body));
+        match ty {
+            Sum => self.node(NodeType::Sum(index, lower, upper, body), start),
+            _ => self.node(NodeType::Product(index, lower, upper, body), start),
+        }
+    }
+
+    /// Parses an integral after the sign, either as `(a, b, f, x)` or in the written form
+    /// `₀^π f dx`, with the bounds as a subscript and a superscript or `^`
+    fn integral(&mut self, start: usize) -> ParseResult {
+        if self.token.ty == LeftParen {
+            let list_start = self.token.range.start;
+            self.advance();
+            let mut args = self.list(list_start, RightParen)?;
+            if args.len() != 4 {
+                return self.error(
+                    "invalid integral".to_string(),
+                    "expected the lower bound, upper bound, integrand, and variable".to_string(),
+                    start,
+                );
+            }
+            let NodeType::Identifier(var) = args.pop().unwrap().ty else {
+                return self.error(
+                    "invalid integral".to_string(),
+                    "the last argument should be the variable of integration".to_string(),
+                    start,
+                );
+            };
+            let body = args.pop().unwrap();
+            let upper = args.pop().unwrap();
+            let lower = args.pop().unwrap();
+            return self.node(
+                NodeType::Integral(var, Box::new(lower), Box::new(upper), Box::new(body)),
+                start,
+            );
+        }
+
+        let Subscript(tokens) = self.token.ty.clone() else {
+            return self.error(
+                "expected token".to_string(),
+                format!("expected {} or a subscript lower bound", LeftParen),
+                start,
+            );
+        };
+        self.advance();
+        let lower = Parser::new(tokens).arith_expr()?;
+
+        let upper = match self.token.ty.clone() {
+            Superscript(tokens) => {
+                self.advance();
+                Parser::new(tokens).arith_expr()?
+            }
+            Carrot => {
+                self.advance();
+                self.postfix()?
+            }
+            _ => {
+                return self.error(
+                    "expected token".to_string(),
+                    format!("expected {} or a superscript upper bound", Carrot),
+                    start,
+                )
+            }
+        };
+
+        let in_integral = self.in_integral;
+        self.in_integral = true;
+        let body = self.arith_expr();
+        self.in_integral = in_integral;
+        let body = body?;
+
+        let var = match &self.token.ty {
+            Identifier(name) => differential(name),
+            _ => None,
+        };
+        let Some(var) = var else {
+            return self.error(
+                "expected token".to_string(),
+                "expected the differential, like dx".to_string(),
+                start,
+            );
+        };
+        self.advance();
+
+        self.node(
+            NodeType::Integral(var, Box::new(lower), Box::new(upper), Box::new(body)),
+            start,
+        )
+    }
+
+    /// Parses what's inside braces after the opening one, which is either a piecewise
+    /// expression or a block of statements, depending on whether the first thing in it is
+    /// followed by a condition
+    fn braces(&mut self, start: usize) -> ParseResult {
+        self.skip_newlines();
+        if self.token.ty == RightBrace {
+            return self.piecewise(start, None);
+        }
+
+        let first = self.statement()?;
+        match self.token.ty {
+            If | Otherwise => self.piecewise(start, Some(first)),
+            _ => self.block(start, first),
+        }
+    }
+
+    /// Parses the rest of a block like `{ a = x²; a + 1 }` after its first statement. The
+    /// statements are separated by semicolons or newlines.
+    fn block(&mut self, start: usize, first: Node) -> ParseResult {
+        let mut statements = vec![first];
+        while self.token.ty != RightBrace {
+            if self.token.ty == EOF {
+                return self.error(
+                    "expected token".to_string(),
+                    format!("expected {}", RightBrace),
+                    start,
+                );
+            }
+            if self.skip_separators() == 0 {
+                return self.error(
+                    "expected token".to_string(),
+                    format!("expected {}, {}, or {}", Semicolon, Newline, RightBrace),
+                    start,
+                );
+            }
+            if !matches!(self.token.ty, RightBrace | EOF) {
+                statements.push(self.statement()?);
+            }
+        }
+        self.advance();
+
+        self.node(NodeType::Block(statements), start)
+    }
+
+    /// Parses the cases of a piecewise expression after the opening brace, like
+    /// `{ x if x ≥ 0, -x otherwise }`, into nested conditionals. `first` is the value of the
+    /// first case, if it's already been parsed.
+    fn piecewise(&mut self, start: usize, mut first: Option<Node>) -> ParseResult {
+        let mut cases: Vec<(Node, Node)> = vec![];
+        let mut otherwise: Option<Node> = None;
+
+        while self.token.ty != RightBrace {
+            if otherwise.is_some() {
+                return self.error(
+                    "unexpected case".to_string(),
+                    format!("the {} case must come last", Otherwise),
+                    start,
+                );
+            }
+
+            let value = match first.take() {
+                Some(value) => value,
+                None => self.or_expr()?,
+            };
+            match self.token.ty {
+                If => {
+                    self.advance();
+                    let condition = self.or_expr()?;
+                    cases.push((value, condition));
+                }
+                Otherwise => {
+                    self.advance();
+                    otherwise = Some(value);
+                }
+                _ => {
+                    return self.error(
+                        "expected token".to_string(),
+                        format!("expected {} or {}", If, Otherwise),
+                        start,
+                    )
+                }
+            }
+
+            match self.token.ty {
+                Comma | Newline => {
+                    self.advance();
+                    self.skip_newlines();
+                }
+                RightBrace => {}
+                _ => {
+                    return self.error(
+                        "expected token".to_string(),
+                        format!("expected {}, {}, or {}", Comma, Newline, RightBrace),
+                        start,
+                    )
+                }
+            }
+        }
+        let range = start..self.token.range.end;
+        self.advance();
+
+        let mut result = otherwise.map(Box::new);
+        for (value, condition) in cases.into_iter().rev() {
+            result = Some(Box::new(Node {
+                ty: NodeType::If(Box::new(condition), Box::new(value), result),
+                range: range.clone(),
+            }));
+        }
+
+        match result {
+            Some(node) => Ok(*node),
+            None => self.error(
+                "empty piecewise".to_string(),
+                "there should be at least one case here".to_string(),
+                start,
+            ),
+        }
+    }
+
+    /// Parses a vector like `[1, 2, 3]`, or a matrix with rows separated by semicolons or
+    /// newlines like `[1, 2; 3, 4]`, after the opening bracket
+    fn brackets(&mut self, start: usize) -> ParseResult {
+        let mut rows: Vec<Vec<Node>> = vec![vec![]];
+        self.skip_newlines();
+
+        while self.token.ty != RightBracket {
+            rows.last_mut().unwrap().push(self.expr()?);
+            match self.token.ty {
+                Comma => self.advance(),
+                Semicolon | Newline => {
+                    self.advance();
+                    self.skip_newlines();
+                    if self.token.ty != RightBracket {
+                        rows.push(vec![]);
+                    }
+                }
+                RightBracket => {}
+                _ => {
+                    return self.error(
+                        "expected token".to_string(),
+                        format!("expected {}, {}, or {}", Comma, Semicolon, RightBracket),
+                        start,
+                    )
+                }
+            }
+        }
+        self.advance();
+
+        if rows.len() == 1 {
+            return self.node(NodeType::Vector(rows.pop().unwrap()), start);
+        }
+        if rows.iter().any(|row| row.len() != rows[0].len()) {
+            return self.error(
+                "invalid matrix".to_string(),
+                "every row of a matrix needs the same number of entries".to_string(),
+                start,
+            );
+        }
+        self.node(NodeType::Matrix(rows), start)
+    }
+
     fn list(&mut self, start: usize, end: TokenType) -> Result<Vec<Node>, AmiError> {
         let mut nodes: Vec<Node> = vec![];
 
@@ -458,3 +1011,219 @@ impl Parser {
         Ok(nodes)
     }
 }
+
+/// The variable of a differential like `dx`
+fn differential(name: &str) -> Option<Rc<str>> {
+    match name.strip_prefix('d') {
+        Some(var) if !var.is_empty() => Some(var.into()),
+        _ => None,
+    }
+}
+
+/// Whether a token can start the operand of `d/dx`, rather than follow a division by `dx`
+fn starts_operand(ty: &TokenType) -> bool {
+    matches!(
+        ty,
+        Number(_)
+            | Bool(_)
+            | Identifier(_)
+            | LeftParen
+            | Pipe
+            | LeftFloor
+            | LeftCeil
+            | LeftBrace
+            | LeftBracket
+            | LeftAngle
+            | Sum
+            | Product
+            | Integral
+            | Sqrt
+            | Cbrt
+            | Fort
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Parser;
+    use crate::Lexer;
+
+    fn parse(source: &str) -> String {
+        let tokens = Lexer::new(source).lex().ok().unwrap();
+        let node = Parser::new(tokens).statement().ok().unwrap();
+        node.to_string()
+    }
+
+    #[test]
+    fn subtraction_is_left_associative() {
+        assert_eq!(parse("10 - 3 - 2"), "((10 - 3) - 2)");
+        assert_eq!(parse("1 + 2 - 3 + 4"), "(((1 + 2) - 3) + 4)");
+    }
+
+    #[test]
+    fn division_is_left_associative() {
+        assert_eq!(parse("8 / 4 / 2"), "((8 ÷ 4) ÷ 2)");
+        assert_eq!(parse("8 ÷ 4 × 2"), "((8 ÷ 4) × 2)");
+        assert_eq!(parse("8 / 4 ∙ 2"), "((8 ÷ 4) ∙ 2)");
+    }
+
+    #[test]
+    fn modulo_is_left_associative() {
+        assert_eq!(parse("20 mod 7 mod 4"), "((20 mod 7) mod 4)");
+        assert_eq!(parse("20 % 7 * 3"), "((20 mod 7) × 3)");
+    }
+
+    #[test]
+    fn powers_are_right_associative() {
+        assert_eq!(parse("2 ^ 3 ^ 2"), "(2 ^ (3 ^ 2))");
+        assert_eq!(parse("2^3²"), "(2 ^ (3 ^ 2))");
+        assert_eq!(parse("x²³"), "(x ^ 23)");
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(parse("1 + 2 * 3 - 4"), "((1 + (2 × 3)) - 4)");
+        assert_eq!(parse("-2^2"), "(-(2 ^ 2))");
+        assert_eq!(parse("2 * 3^2 / 4"), "((2 × (3 ^ 2)) ÷ 4)");
+    }
+
+    #[test]
+    fn implicit_multiplication() {
+        assert_eq!(parse("2x"), "(2 × x)");
+        assert_eq!(parse("1/2x"), "(1 ÷ (2 × x))");
+        assert_eq!(parse("-2x²"), "(-(2 × (x ^ 2)))");
+    }
+
+    #[test]
+    fn number_literals() {
+        assert_eq!(parse("0xff + 0o17 + 0b1010"), "((255 + 15) + 10)");
+        assert_eq!(parse("1_000_000.5"), "1000000.5");
+        assert_eq!(parse("1.5e3 - 2E-3"), "(1.5e3 - 2e-3)");
+        assert_eq!(parse("6.022×10²³"), "6.022e23");
+        assert_eq!(parse("2×10⁻³"), "2e-3");
+        assert_eq!(parse("2e"), "(2 × e)");
+        assert_eq!(parse("2×10²ˣ"), "(2 × (10 ^ (2 × x)))");
+    }
+
+    #[test]
+    fn matrices() {
+        assert_eq!(parse("[1, 2; 3, 4]"), "[1, 2; 3, 4]");
+        assert_eq!(parse("[1, 2\n 3, 4\n]"), "[1, 2; 3, 4]");
+        assert_eq!(parse("[1, 2;]"), "[1, 2]");
+    }
+
+    #[test]
+    fn series() {
+        assert_eq!(parse("Σ(k=1, n, k²)"), "Σ(k = 1, n, (k ^ 2))");
+        assert_eq!(parse("∏(k = 1, 5, k) + 1"), "(Π(k = 1, 5, k) + 1)");
+        assert_eq!(
+            parse("2Σ(k=0, ∞, 1/2^k)"),
+            "(2 × Σ(k = 0, ∞, (1 ÷ (2 ^ k))))"
+        );
+    }
+
+    #[test]
+    fn integrals() {
+        assert_eq!(parse("∫(0, π, sin(x), x)"), "∫(0, π, sin(x), x)");
+        assert_eq!(parse("∫₀^π sin(x) dx"), "∫(0, π, sin(x), x)");
+        assert_eq!(parse("∫₀¹ 2 dt + 1"), "(∫(0, 1, 2, t) + 1)");
+        assert_eq!(parse("∫₋₁^∞ 2x dx"), "∫((-1), ∞, (2 × x), x)");
+    }
+
+    #[test]
+    fn derivatives() {
+        assert_eq!(parse("d/dx x^2"), "(d/dx (x ^ 2))");
+        assert_eq!(parse("d/dx sin(x) + 1"), "(d/dx (sin(x) + 1))");
+        assert_eq!(parse("2 + d/dx x"), "(2 + (d/dx x))");
+        assert_eq!(parse("d/2"), "(d ÷ 2)");
+        assert_eq!(parse("d/dt"), "(d ÷ dt)");
+        assert_eq!(parse("d/dt * 2"), "((d ÷ dt) × 2)");
+        assert_eq!(parse("derive(f, t)"), "(d/dt f)");
+        assert_eq!(parse("f''(2)"), "f''(2)");
+    }
+
+    #[test]
+    fn equations() {
+        assert_eq!(
+            parse("solve(x² - 2 = 0, x)"),
+            "solve((((x ^ 2) - 2) = 0), x)"
+        );
+        assert_eq!(parse("solve(x = 2y, x)"), "solve((x = (2 × y)), x)");
+        assert_eq!(parse("solve(cos(x) = x, x)"), "solve((cos(x) = x), x)");
+        assert_eq!(parse("solve(f, 1)"), "solve(f, 1)");
+    }
+
+    #[test]
+    fn lambdas() {
+        assert_eq!(parse("x ↦ x²"), "(x ↦ (x ^ 2))");
+        assert_eq!(parse("(x, y) -> x + y"), "((x, y) ↦ (x + y))");
+        assert_eq!(parse("f = x -> 2x"), "(f = (x ↦ (2 × x)))");
+        assert_eq!(parse("map(x ↦ x, v)"), "map((x ↦ x), v)");
+        assert_eq!(parse("(x + y) - 1"), "((x + y) - 1)");
+        assert_eq!(parse("f ∘ g"), "(f ∘ g)");
+    }
+
+    #[test]
+    fn calls_of_expressions() {
+        assert_eq!(parse("(f ∘ g)(3)"), "(f ∘ g)(3)");
+        assert_eq!(parse("(x ↦ x²)(4)"), "(x ↦ (x ^ 2))(4)");
+        assert_eq!(parse("adder(2)(3)"), "adder(2)(3)");
+        assert_eq!(parse("v[1](0) + 1"), "(v[1](0) + 1)");
+    }
+
+    #[test]
+    fn identifiers() {
+        assert_eq!(parse("θ₁ + aₙ′"), "(θ₁ + aₙ′)");
+        assert_eq!(parse("2ℏ𝜔"), "(2 × ℏ𝜔)");
+        assert_eq!(parse("café·x²"), "(café ∙ (x ^ 2))");
+        assert_eq!(parse("xⁱ"), "(x ^ i)");
+    }
+
+    #[test]
+    fn blocks() {
+        assert_eq!(
+            parse("g = { a = 2; b = a + 1; b / 2 }"),
+            "(g = { (a = 2); (b = (a + 1)); (b ÷ 2) })"
+        );
+        assert_eq!(parse("{\n  a = 1\n\n  a + 1;\n}"), "{ (a = 1); (a + 1) }");
+        assert_eq!(parse("1 + { 2 }"), "(1 + { 2 })");
+        assert_eq!(
+            parse("{ x if x > 0, 0 otherwise }"),
+            "(if (x > 0) then x else 0)"
+        );
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(parse("8 / 2 // halved"), "(8 ÷ 2)");
+        assert_eq!(parse("1 + /* two */ 2 # three"), "(1 + 2)");
+        assert_eq!(parse("[1, 2 # first row\n 3, 4]"), "[1, 2; 3, 4]");
+
+        let tokens = Lexer::new("a # first\n/* b\n */ c").lex().unwrap();
+        let comments: Vec<_> = tokens
+            .iter()
+            .map(|token| token.comments.iter().map(|c| &*c.text).collect::<Vec<_>>())
+            .collect();
+        assert_eq!(
+            comments,
+            [vec![], vec!["# first"], vec!["/* b\n */"], vec![]]
+        );
+        assert_eq!(tokens[2].ty, super::Identifier("c".into()));
+
+        // A block comment over several lines only ends a statement where a newline could
+        let tokens = Lexer::new("a /* b\n */ c").lex().unwrap();
+        assert_eq!(tokens[1].ty, super::Newline);
+        assert_eq!(parse("1 + /* multi\nline */ 2"), "(1 + 2)");
+        assert_eq!(parse("max(1, /* a\nb */ 2)"), "max(1, 2)");
+        assert_eq!(parse("(1 /* a\nb */ + 2)"), "(1 + 2)");
+        assert_eq!(parse("[1, 2 /* a\nb */ 3, 4]"), "[1, 2; 3, 4]");
+    }
+
+    #[test]
+    fn postfix_operators() {
+        assert_eq!(parse("3!²"), "((3!) ^ 2)");
+        assert_eq!(parse("90°"), "(90°)");
+        assert_eq!(parse("7!!"), "(7!!)");
+        assert_eq!(parse("v[1]²"), "(v[1] ^ 2)");
+    }
+}
@@ -0,0 +1,175 @@
+use std::{cmp::Ordering, fmt};
+
+use crate::BigInt;
+
+/// An exact fraction, always kept in lowest terms with a positive denominator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rational {
+    numer: BigInt,
+    denom: BigInt,
+}
+
+impl Rational {
+    /// Returns `None` when the denominator is zero
+    pub fn new(numer: BigInt, denom: BigInt) -> Option<Self> {
+        if denom.is_zero() {
+            return None;
+        }
+
+        let gcd = numer.gcd(&denom);
+        let (mut numer, mut denom) = if gcd.is_one() {
+            (numer, denom)
+        } else {
+            (numer.div_rem(&gcd)?.0, denom.div_rem(&gcd)?.0)
+        };
+        if denom.is_negative() {
+            numer = -&numer;
+            denom = -&denom;
+        }
+        Some(Self { numer, denom })
+    }
+
+    pub fn numer(&self) -> &BigInt {
+        &self.numer
+    }
+
+    pub fn denom(&self) -> &BigInt {
+        &self.denom
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.denom.is_one()
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let (numer, denom) = (self.numer.to_f64(), self.denom.to_f64());
+        if numer.is_finite() && denom.is_finite() {
+            return numer / denom;
+        }
+
+        // Scale so the quotient keeps about 17 significant digits before converting
+        let digits = |n: &BigInt| n.abs().to_string().len() as i32;
+        let shift = 17 - (digits(&self.numer) - digits(&self.denom));
+        let ten = BigInt::from(10);
+        let (quotient, _) = if shift >= 0 {
+            (&self.numer * &ten.pow(shift as u32)).div_rem(&self.denom)
+        } else {
+            self.numer.div_rem(&(&self.denom * &ten.pow(-shift as u32)))
+        }
+        .unwrap();
+        quotient.to_f64() * 10f64.powi(-shift)
+    }
+
+    pub fn neg(&self) -> Self {
+        Self {
+            numer: -&self.numer,
+            denom: self.denom.clone(),
+        }
+    }
+
+    pub fn abs(&self) -> Self {
+        Self {
+            numer: self.numer.abs(),
+            denom: self.denom.clone(),
+        }
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        Self::new(
+            &(&self.numer * &other.denom) + &(&other.numer * &self.denom),
+            &self.denom * &other.denom,
+        )
+        .unwrap()
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        Self::new(&self.numer * &other.numer, &self.denom * &other.denom).unwrap()
+    }
+
+    pub fn div(&self, other: &Self) -> Option<Self> {
+        Self::new(&self.numer * &other.denom, &self.denom * &other.numer)
+    }
+
+    pub fn pow(&self, exponent: i64) -> Option<Self> {
+        let power = exponent.unsigned_abs() as u32;
+        let (numer, denom) = (self.numer.pow(power), self.denom.pow(power));
+        if exponent < 0 {
+            Self::new(denom, numer)
+        } else {
+            Self::new(numer, denom)
+        }
+    }
+
+    pub fn trunc(&self) -> BigInt {
+        self.numer.div_rem(&self.denom).unwrap().0
+    }
+
+    pub fn floor(&self) -> BigInt {
+        let trunc = self.trunc();
+        if self.numer.is_negative() && !self.is_integer() {
+            &trunc - &BigInt::one()
+        } else {
+            trunc
+        }
+    }
+
+    pub fn ceil(&self) -> BigInt {
+        let trunc = self.trunc();
+        if !self.numer.is_negative() && !self.is_integer() {
+            &trunc + &BigInt::one()
+        } else {
+            trunc
+        }
+    }
+
+    /// Rounds half away from zero, like `f64::round`
+    pub fn round(&self) -> BigInt {
+        let half = Self::new(BigInt::one(), BigInt::from(2)).unwrap();
+        if self.numer.is_negative() {
+            self.sub(&half).ceil()
+        } else {
+            self.add(&half).floor()
+        }
+    }
+
+    /// Truncated remainder, matching `%` on integers and floats
+    pub fn rem(&self, other: &Self) -> Option<Self> {
+        let quotient = self.div(other)?.trunc();
+        Some(self.sub(&other.mul(&quotient.into())))
+    }
+}
+
+impl From<BigInt> for Rational {
+    fn from(value: BigInt) -> Self {
+        Self {
+            numer: value,
+            denom: BigInt::one(),
+        }
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.numer * &other.denom).cmp(&(&other.numer * &self.denom))
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.numer)
+        } else {
+            write!(f, "{}/{}", self.numer, self.denom)
+        }
+    }
+}
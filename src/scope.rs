@@ -1,27 +1,176 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt, ptr,
+    rc::{Rc, Weak},
+};
 
 use crate::Value;
 
+/// An environment of variables. Scopes are shared, so functions can keep the scope they were
+/// defined in alive and see its variables when they're called later.
+///
+/// A scope only has a weak reference to its parent. Whatever uses a scope keeps the ones around
+/// it alive too: an interpreter running in it, or the [`Captured`] scopes of a function.
 #[derive(Default)]
-pub struct Scope<'a> {
-    pub variables: HashMap<Rc<str>, Value>,
-    pub parent: Option<&'a Scope<'a>>,
+pub struct Scope {
+    variables: RefCell<HashMap<Rc<str>, Value>>,
+    parent: Option<Weak<Scope>>,
 }
 
-impl Scope<'_> {
-    pub fn get(&self, name: &str) -> Value {
-        if let Some(value) = self.variables.get(name) {
-            return value.clone();
+impl Scope {
+    /// A new empty scope inside `parent`
+    pub fn child(parent: &Rc<Scope>) -> Rc<Self> {
+        Rc::new(Self {
+            variables: RefCell::new(HashMap::new()),
+            parent: Some(Rc::downgrade(parent)),
+        })
+    }
+
+    fn parent(&self) -> Option<Rc<Scope>> {
+        self.parent.as_ref().and_then(Weak::upgrade)
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        if let Some(value) = self.variables.borrow().get(name) {
+            let mut value = value.clone();
+            for_each_captured(&mut value, &mut Captured::restore);
+            return Some(value);
+        }
+
+        self.parent().and_then(|parent| parent.get(name))
+    }
+
+    pub fn set(&self, name: Rc<str>, mut value: Value) {
+        for_each_captured(&mut value, &mut |captured| captured.store_in(self));
+        self.variables.borrow_mut().insert(name, value);
+    }
+
+    /// This scope and the ones around it, innermost first
+    fn chain(self: Rc<Self>) -> Vec<Rc<Scope>> {
+        let mut chain = vec![self];
+        while let Some(parent) = chain.last().unwrap().parent() {
+            chain.push(parent);
         }
+        chain
+    }
+
+    /// Finds the defined name closest to `name`, if any is close enough to be a likely typo.
+    /// A name has to keep at least one of its characters, so a one letter name never matches.
+    pub fn similar(&self, name: &str) -> Option<Rc<str>> {
+        let length = name.chars().count();
+        let max_distance = (length / 3).max(1).min(length.saturating_sub(1));
+        let mut best: Option<(usize, Rc<str>)> = None;
 
-        if let Some(parent) = self.parent {
-            return parent.get(name);
+        let mut scope: Option<Rc<Scope>> = None;
+        loop {
+            let current = scope.as_deref().unwrap_or(self);
+            for candidate in current.variables.borrow().keys() {
+                let distance = edit_distance(name, candidate);
+                if distance <= max_distance
+                    && best
+                        .as_ref()
+                        .is_none_or(|(d, best)| (distance, &**candidate) < (*d, &**best))
+                {
+                    best = Some((distance, Rc::clone(candidate)));
+                }
+            }
+            match current.parent() {
+                Some(parent) => scope = Some(parent),
+                None => break,
+            }
         }
 
-        Value::Number(0.0)
+        best.map(|(_, name)| name)
+    }
+}
+
+/// The scope a function was defined in, which the function keeps alive along with the scopes
+/// around it.
+///
+/// A function stored in a variable lets go of the scope holding it and the scopes around that,
+/// which are alive as long as anything can read the variable. Otherwise a recursive function
+/// would keep the scope it's stored in alive, and the two would never be freed. Reading the
+/// function back with [`Scope::get`] takes the references again.
+#[derive(Clone)]
+pub struct Captured {
+    scope: Weak<Scope>,
+    /// Strong references to the scope and the ones around it, innermost first
+    kept: Vec<Rc<Scope>>,
+}
+
+impl Captured {
+    pub fn new(scope: &Rc<Scope>) -> Self {
+        Self {
+            scope: Rc::downgrade(scope),
+            kept: Rc::clone(scope).chain(),
+        }
     }
 
-    pub fn set(&mut self, name: Rc<str>, value: Value) {
-        self.variables.insert(name, value);
+    /// The scope the function was defined in
+    pub fn scope(&self) -> Rc<Scope> {
+        self.scope
+            .upgrade()
+            .expect("a function that isn't stored keeps the scope it was defined in alive")
     }
+
+    /// Lets go of `holder` and the scopes around it
+    fn store_in(&mut self, holder: &Scope) {
+        let parents = holder.parent().map(Scope::chain).unwrap_or_default();
+        let shared = self.kept.iter().position(|scope| {
+            ptr::eq(&**scope, holder) || parents.iter().any(|parent| Rc::ptr_eq(parent, scope))
+        });
+        if let Some(shared) = shared {
+            self.kept.truncate(shared);
+        }
+    }
+
+    /// Takes back the references that [`store_in`](Self::store_in) let go of
+    fn restore(&mut self) {
+        self.kept = self.scope().chain();
+    }
+}
+
+impl fmt::Debug for Captured {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Captured").finish_non_exhaustive()
+    }
+}
+
+/// Calls `f` with the captured scope of every function in `value`
+fn for_each_captured(value: &mut Value, f: &mut impl FnMut(&mut Captured)) {
+    match value {
+        Value::Function(function) => f(&mut function.scope),
+        Value::Vector(elements) => {
+            for element in elements {
+                for_each_captured(element, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Functions capture the scope they're defined in, which usually contains them, so printing the
+// variables would never end
+impl fmt::Debug for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scope").finish_non_exhaustive()
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev + usize::from(ca != *cb);
+            prev = row[j + 1];
+            row[j + 1] = substitution.min(prev + 1).min(row[j] + 1);
+        }
+    }
+
+    row[b.len()]
 }
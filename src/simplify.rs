@@ -0,0 +1,486 @@
+use crate::{arithmetic, BigInt, BinaryOp, Node, NodeType, UnaryOp, Value};
+
+/// Passes are repeated until nothing changes, up to this many times
+const MAX_PASSES: usize = 8;
+
+/// Rewrites an expression into a simpler equivalent one: constants are folded, like terms and
+/// repeated factors are combined, and identities like `x × 1`, `x + 0` and `x^1` are dropped.
+/// The result has no source ranges, since it doesn't correspond to any source text.
+pub fn simplify(node: &Node) -> Node {
+    let mut node = pass(node);
+    for _ in 1..MAX_PASSES {
+        let next = pass(&node);
+        if next == node {
+            break;
+        }
+        node = next;
+    }
+    node
+}
+
+fn pass(node: &Node) -> Node {
+    match &node.ty {
+        NodeType::Binary(_, BinaryOp::Add | BinaryOp::Sub, _)
+        | NodeType::Unary(UnaryOp::Pos | UnaryOp::Neg, _) => {
+            let mut terms = vec![];
+            collect_terms(node, Value::from(1), &mut terms);
+            sum(terms)
+        }
+        // A dot product with a number is an ordinary product, whatever the other side is
+        NodeType::Binary(left, BinaryOp::Dot, right) => {
+            let (left, right) = (pass(left), pass(right));
+            if constant(&left).is_some() || constant(&right).is_some() {
+                pass(&Node::from(NodeType::Binary(
+                    Box::new(left),
+                    BinaryOp::Mul,
+                    Box::new(right),
+                )))
+            } else {
+                Node::from(NodeType::Binary(
+                    Box::new(left),
+                    BinaryOp::Dot,
+                    Box::new(right),
+                ))
+            }
+        }
+        NodeType::Binary(_, BinaryOp::Mul | BinaryOp::Div, _) => {
+            let mut factors = Factors {
+                coefficient: Value::from(1),
+                factors: vec![],
+            };
+            factors.collect(node, false);
+            factors.build()
+        }
+        NodeType::Binary(base, BinaryOp::Pow, exponent) => power(pass(base), pass(exponent)),
+        NodeType::Unary(op, operand) => {
+            let operand = pass(operand);
+            match constant(&operand) {
+                Some(x) => fold(arithmetic::unary(*op, x.clone()), &[x]),
+                None => None,
+            }
+            .unwrap_or_else(|| Node::from(NodeType::Unary(*op, Box::new(operand))))
+        }
+        _ => {
+            let mut node = node.map_children(pass);
+            node.range = 0..0;
+            node
+        }
+    }
+}
+
+/// Flattens a sum into `(coefficient, term)` pairs, adding up the coefficients of like terms.
+/// The constant part of the sum has no term.
+fn collect_terms(node: &Node, scale: Value, terms: &mut Vec<(Value, Option<Node>)>) {
+    let negated = || arithmetic::unary(UnaryOp::Neg, scale.clone()).unwrap();
+    match &node.ty {
+        NodeType::Binary(left, BinaryOp::Add, right) => {
+            collect_terms(left, scale.clone(), terms);
+            collect_terms(right, scale, terms);
+        }
+        NodeType::Binary(left, BinaryOp::Sub, right) => {
+            collect_terms(left, scale.clone(), terms);
+            collect_terms(right, negated(), terms);
+        }
+        NodeType::Unary(UnaryOp::Neg, operand) => collect_terms(operand, negated(), terms),
+        NodeType::Unary(UnaryOp::Pos, operand) => collect_terms(operand, scale, terms),
+        _ => {
+            let term = pass(node);
+            // Simplifying can turn a term into a sum, like 2(x + 1) into 2x + 2
+            if term != *node && is_sum(&term) {
+                return collect_terms(&term, scale, terms);
+            }
+
+            let (coefficient, term) = split_coefficient(&term);
+            let Ok(coefficient) = arithmetic::binary(BinaryOp::Mul, scale, coefficient) else {
+                return;
+            };
+            match terms.iter_mut().find(|(_, other)| *other == term) {
+                Some((total, _)) => {
+                    if let Ok(sum) = arithmetic::binary(BinaryOp::Add, total.clone(), coefficient) {
+                        *total = sum;
+                    }
+                }
+                None => terms.push((coefficient, term)),
+            }
+        }
+    }
+}
+
+/// Writes the terms back out as a sum, with the constant last, like `2x - y + 1`
+fn sum(mut terms: Vec<(Value, Option<Node>)>) -> Node {
+    terms.retain(|(coefficient, _)| !is(coefficient, 0.0));
+    terms.sort_by_key(|(_, term)| term.is_none());
+
+    let mut result: Option<Node> = None;
+    for (coefficient, term) in terms {
+        let negative = is_negative(&coefficient);
+        let magnitude = if negative {
+            arithmetic::unary(UnaryOp::Neg, coefficient).unwrap()
+        } else {
+            coefficient
+        };
+        let node = match term {
+            None => value_node(&magnitude),
+            Some(term) if is(&magnitude, 1.0) => term,
+            Some(term) => pass(&Node::from(NodeType::Binary(
+                Box::new(value_node(&magnitude)),
+                BinaryOp::Mul,
+                Box::new(term),
+            ))),
+        };
+        result = Some(match (result, negative) {
+            (None, false) => node,
+            (None, true) => Node::from(NodeType::Unary(UnaryOp::Neg, Box::new(node))),
+            (Some(sum), false) => Node::from(NodeType::Binary(
+                Box::new(sum),
+                BinaryOp::Add,
+                Box::new(node),
+            )),
+            (Some(sum), true) => Node::from(NodeType::Binary(
+                Box::new(sum),
+                BinaryOp::Sub,
+                Box::new(node),
+            )),
+        });
+    }
+    result.unwrap_or_else(|| value_node(&Value::from(0)))
+}
+
+/// Splits a simplified term like `-3x` into its numeric coefficient and the rest
+fn split_coefficient(node: &Node) -> (Value, Option<Node>) {
+    if let Some(x) = constant(node) {
+        return (x, None);
+    }
+    match &node.ty {
+        NodeType::Unary(UnaryOp::Neg, operand) => {
+            let (coefficient, term) = split_coefficient(operand);
+            (arithmetic::unary(UnaryOp::Neg, coefficient).unwrap(), term)
+        }
+        NodeType::Binary(left, BinaryOp::Mul, right) => match constant(left) {
+            Some(coefficient) => (coefficient, Some((**right).clone())),
+            None => (Value::from(1), Some(node.clone())),
+        },
+        NodeType::Binary(left, BinaryOp::Div, right) => {
+            let (coefficient, term) = split_coefficient(left);
+            match constant(right)
+                .and_then(|d| arithmetic::binary(BinaryOp::Div, coefficient, d).ok())
+            {
+                Some(coefficient) => (coefficient, Some(term.unwrap_or_else(|| node.clone()))),
+                None => (Value::from(1), Some(node.clone())),
+            }
+        }
+        _ => (Value::from(1), Some(node.clone())),
+    }
+}
+
+/// A product split into a numeric coefficient and `(base, exponent)` factors, where division
+/// shows up as negative exponents
+struct Factors {
+    coefficient: Value,
+    factors: Vec<(Node, Node)>,
+}
+
+impl Factors {
+    fn collect(&mut self, node: &Node, inverse: bool) {
+        match &node.ty {
+            NodeType::Binary(left, BinaryOp::Mul, right) => {
+                self.collect(left, inverse);
+                self.collect(right, inverse);
+            }
+            NodeType::Binary(left, BinaryOp::Div, right) => {
+                self.collect(left, inverse);
+                self.collect(right, !inverse);
+            }
+            NodeType::Unary(UnaryOp::Neg, operand) => {
+                self.coefficient =
+                    arithmetic::unary(UnaryOp::Neg, self.coefficient.clone()).unwrap();
+                self.collect(operand, inverse);
+            }
+            _ => {
+                let factor = pass(node);
+                if factor != *node
+                    && matches!(
+                        factor.ty,
+                        NodeType::Binary(_, BinaryOp::Mul | BinaryOp::Div, _)
+                            | NodeType::Unary(UnaryOp::Neg, _)
+                    )
+                {
+                    return self.collect(&factor, inverse);
+                }
+
+                if let Some(x) = constant(&factor) {
+                    let op = if inverse {
+                        BinaryOp::Div
+                    } else {
+                        BinaryOp::Mul
+                    };
+                    if let Ok(coefficient) = arithmetic::binary(op, self.coefficient.clone(), x) {
+                        self.coefficient = coefficient;
+                        return;
+                    }
+                }
+
+                let (base, exponent) = match factor.ty {
+                    NodeType::Binary(base, BinaryOp::Pow, exponent) => (*base, *exponent),
+                    ty => (Node::from(ty), value_node(&Value::from(1))),
+                };
+                let exponent = if inverse {
+                    pass(&Node::from(NodeType::Unary(
+                        UnaryOp::Neg,
+                        Box::new(exponent),
+                    )))
+                } else {
+                    exponent
+                };
+
+                // Repeated factors are combined, so x × x^2 is x^3 and x ÷ x is 1
+                match self.factors.iter_mut().find(|(other, _)| *other == base) {
+                    Some((_, total)) => {
+                        *total = pass(&Node::from(NodeType::Binary(
+                            Box::new(total.clone()),
+                            BinaryOp::Add,
+                            Box::new(exponent),
+                        )))
+                    }
+                    None => self.factors.push((base, exponent)),
+                }
+            }
+        }
+    }
+
+    fn build(mut self) -> Node {
+        self.factors
+            .retain(|(_, exponent)| constant(exponent).is_none_or(|x| !is(&x, 0.0)));
+        if is(&self.coefficient, 0.0) || self.factors.is_empty() {
+            return value_node(&self.coefficient);
+        }
+
+        // Numbers are distributed over a sum, so 2(x + 1) is 2x + 2
+        if let [(base, exponent)] = self.factors.as_slice() {
+            if is_sum(base) && constant(exponent).is_some_and(|x| is(&x, 1.0)) {
+                let mut terms = vec![];
+                collect_terms(base, self.coefficient, &mut terms);
+                return sum(terms);
+            }
+        }
+
+        let negative = is_negative(&self.coefficient);
+        let magnitude = if negative {
+            arithmetic::unary(UnaryOp::Neg, self.coefficient).unwrap()
+        } else {
+            self.coefficient
+        };
+        let (numer, denom) = match &magnitude {
+            Value::Rational(r) => (
+                Value::Integer(r.numer().clone()),
+                Value::Integer(r.denom().clone()),
+            ),
+            _ => (magnitude, Value::from(1)),
+        };
+
+        let mut numerator: Vec<Node> = vec![];
+        let mut denominator: Vec<Node> = vec![];
+        if !is(&numer, 1.0) {
+            numerator.push(value_node(&numer));
+        }
+        if !is(&denom, 1.0) {
+            denominator.push(value_node(&denom));
+        }
+        for (base, exponent) in self.factors {
+            match constant(&exponent) {
+                Some(x) if is_negative(&x) => {
+                    let x = arithmetic::unary(UnaryOp::Neg, x).unwrap();
+                    denominator.push(power(base, value_node(&x)));
+                }
+                _ => numerator.push(power(base, exponent)),
+            }
+        }
+
+        let product = |factors: Vec<Node>| {
+            factors.into_iter().reduce(|product, factor| {
+                Node::from(NodeType::Binary(
+                    Box::new(product),
+                    BinaryOp::Mul,
+                    Box::new(factor),
+                ))
+            })
+        };
+        let numerator = product(numerator).unwrap_or_else(|| value_node(&Value::from(1)));
+        let result = match product(denominator) {
+            Some(denominator) => Node::from(NodeType::Binary(
+                Box::new(numerator),
+                BinaryOp::Div,
+                Box::new(denominator),
+            )),
+            None => numerator,
+        };
+        if negative {
+            Node::from(NodeType::Unary(UnaryOp::Neg, Box::new(result)))
+        } else {
+            result
+        }
+    }
+}
+
+/// `base^exponent` for simplified operands
+fn power(base: Node, exponent: Node) -> Node {
+    match (constant(&base), constant(&exponent)) {
+        (_, Some(b)) if is(&b, 0.0) => return value_node(&Value::from(1)),
+        (_, Some(b)) if is(&b, 1.0) => return base,
+        (Some(a), _) if is(&a, 1.0) => return base,
+        (Some(a), Some(b)) => {
+            if let Some(node) = fold(
+                arithmetic::binary(BinaryOp::Pow, a.clone(), b.clone()),
+                &[a, b],
+            ) {
+                return node;
+            }
+        }
+        _ => {}
+    }
+
+    match base.ty {
+        // (x^a)^b = x^(ab) when b is an integer. Otherwise it only holds for x ≥ 0, but an even
+        // power can't be negative, so (x^2)^(1/2) is |x|. Odd powers are left alone, since
+        // (x^3)^(1/3) is a complex root of x when x < 0.
+        NodeType::Binary(inner_base, BinaryOp::Pow, inner)
+            if is_integer(&exponent) || is_even(&inner) =>
+        {
+            let integer_power = is_integer(&exponent);
+            let product = pass(&Node::from(NodeType::Binary(
+                inner,
+                BinaryOp::Mul,
+                Box::new(exponent),
+            )));
+            if integer_power || is_even(&product) {
+                power(*inner_base, product)
+            } else {
+                let abs = Node::from(NodeType::Unary(UnaryOp::Abs, inner_base));
+                power(abs, product)
+            }
+        }
+        ty => Node::from(NodeType::Binary(
+            Box::new(Node::from(ty)),
+            BinaryOp::Pow,
+            Box::new(exponent),
+        )),
+    }
+}
+
+/// The value of a numeric constant, including fractions and negatives like `-1/2`
+fn constant(node: &Node) -> Option<Value> {
+    match &node.ty {
+        NodeType::Number(x) if !x.contains(['.', 'e']) => {
+            x.parse::<BigInt>().ok().map(Value::Integer)
+        }
+        NodeType::Number(x) => x.parse::<f64>().ok().map(Value::Number),
+        NodeType::Unary(UnaryOp::Neg, operand) => {
+            arithmetic::unary(UnaryOp::Neg, constant(operand)?).ok()
+        }
+        NodeType::Binary(left, BinaryOp::Div, right) => {
+            arithmetic::binary(BinaryOp::Div, constant(left)?, constant(right)?).ok()
+        }
+        _ => None,
+    }
+}
+
+/// Folds a computed constant unless that would lose exactness, so `2^3` becomes `8` but `√2`
+/// stays as it is
+fn fold(result: Result<Value, String>, inputs: &[Value]) -> Option<Node> {
+    let value = result.ok()?;
+    let exact = matches!(value, Value::Integer(_) | Value::Rational(_));
+    let from_float = inputs.iter().any(|x| matches!(x, Value::Number(_)));
+    match value.as_f64() {
+        Some(x) if x.is_finite() && (exact || from_float) => Some(value_node(&value)),
+        _ => None,
+    }
+}
+
+fn value_node(value: &Value) -> Node {
+    if is_negative(value) {
+        let magnitude = arithmetic::unary(UnaryOp::Neg, value.clone()).unwrap();
+        return Node::from(NodeType::Unary(
+            UnaryOp::Neg,
+            Box::new(value_node(&magnitude)),
+        ));
+    }
+    match value {
+        Value::Rational(r) => Node::from(NodeType::Binary(
+            Box::new(Node::from(NodeType::Number(r.numer().to_string().into()))),
+            BinaryOp::Div,
+            Box::new(Node::from(NodeType::Number(r.denom().to_string().into()))),
+        )),
+        value => Node::from(NodeType::Number(value.to_string().into())),
+    }
+}
+
+fn is(value: &Value, x: f64) -> bool {
+    value.as_f64() == Some(x)
+}
+
+fn is_negative(value: &Value) -> bool {
+    value.as_f64().is_some_and(|x| x < 0.0)
+}
+
+fn is_integer(node: &Node) -> bool {
+    match constant(node) {
+        Some(Value::Integer(_)) => true,
+        Some(Value::Number(x)) => x.fract() == 0.0,
+        _ => false,
+    }
+}
+
+fn is_even(node: &Node) -> bool {
+    match constant(node) {
+        Some(Value::Integer(n)) => n.is_even(),
+        Some(Value::Number(x)) => x % 2.0 == 0.0,
+        _ => false,
+    }
+}
+
+fn is_sum(node: &Node) -> bool {
+    matches!(
+        node.ty,
+        NodeType::Binary(_, BinaryOp::Add | BinaryOp::Sub, _)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::simplify;
+    use crate::{Lexer, Parser};
+
+    fn simplified(source: &str) -> String {
+        let tokens = Lexer::new(source).lex().unwrap();
+        let node = Parser::new(tokens).statement().unwrap();
+        format!("{:#}", simplify(&node))
+    }
+
+    #[test]
+    fn folds_constants() {
+        assert_eq!(simplified("2 + 3 * 4"), "14");
+        assert_eq!(simplified("1/3 + 1/6"), "1 ÷ 2");
+        assert_eq!(simplified("√2 * 1"), "√2");
+    }
+
+    #[test]
+    fn combines_like_terms_and_factors() {
+        assert_eq!(simplified("x + x + 1 - 1"), "2x");
+        assert_eq!(simplified("x * x² / x"), "x^2");
+        assert_eq!(simplified("2(x + 1) - 2"), "2x");
+        assert_eq!(simplified("x * 1 + 0 * y"), "x");
+        assert_eq!(simplified("0·x + 1"), "1");
+        assert_eq!(simplified("2·3·x + x·1"), "7x");
+        assert_eq!(simplified("x·y"), "x ∙ y");
+    }
+
+    #[test]
+    fn powers_of_powers() {
+        assert_eq!(simplified("(x²)³"), "x^6");
+        assert_eq!(simplified("(x^(1/2))^2"), "x");
+        assert_eq!(simplified("(x^2)^(1/2)"), "|x|");
+        assert_eq!(simplified("(x^4)^(1/2)"), "x^2");
+        assert_eq!(simplified("(x^3)^(1/3)"), "(x^3)^(1 ÷ 3)");
+        assert_eq!(simplified("(x^2)^(1/4)"), "|x|^(1 ÷ 2)");
+    }
+}
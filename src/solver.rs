@@ -0,0 +1,549 @@
+use std::f64::consts::PI;
+
+use crate::{
+    derivative::{flatten, substitute},
+    BinaryOp, Complex, Function, Node, NodeType, Scope, UnaryOp, Value,
+};
+
+/// The largest power that's expanded when reading an equation as a polynomial
+const MAX_EXPANDED_POWER: u32 = 16;
+
+/// Where roots are looked for when there's no initial guess
+pub const SCAN_RANGE: f64 = 100.0;
+const SCAN_STEPS: usize = 2000;
+const MAX_ITERATIONS: usize = 100;
+/// Durand–Kerner converges slowly on repeated roots, so it gets more iterations than Newton
+const MAX_POLYNOMIAL_ITERATIONS: usize = 1000;
+const MAX_INLINE_DEPTH: u32 = 64;
+
+/// Reads `node` as a polynomial in `var`, returning its coefficients from the constant term
+/// up. Parts that don't depend on `var` are evaluated with `constant`.
+pub fn polynomial(
+    node: &Node,
+    var: &str,
+    constant: &mut impl FnMut(&Node) -> Option<f64>,
+) -> Option<Vec<f64>> {
+    if !depends(node, var) {
+        return Some(vec![constant(node)?]);
+    }
+
+    let mut coefficients = match &node.ty {
+        NodeType::Identifier(_) => vec![0.0, 1.0],
+        NodeType::Unary(UnaryOp::Pos, u) => polynomial(u, var, constant)?,
+        NodeType::Unary(UnaryOp::Neg, u) => scale(&polynomial(u, var, constant)?, -1.0),
+        NodeType::Binary(u, BinaryOp::Add, v) => add(
+            &polynomial(u, var, constant)?,
+            &polynomial(v, var, constant)?,
+        ),
+        NodeType::Binary(u, BinaryOp::Sub, v) => add(
+            &polynomial(u, var, constant)?,
+            &scale(&polynomial(v, var, constant)?, -1.0),
+        ),
+        NodeType::Binary(u, BinaryOp::Mul | BinaryOp::Dot | BinaryOp::Cross, v) => multiply(
+            &polynomial(u, var, constant)?,
+            &polynomial(v, var, constant)?,
+        ),
+        NodeType::Binary(u, BinaryOp::Div, v) if !depends(v, var) => {
+            scale(&polynomial(u, var, constant)?, 1.0 / constant(v)?)
+        }
+        NodeType::Binary(u, BinaryOp::Pow, v) if !depends(v, var) => {
+            let exponent = constant(v)?;
+            if exponent.fract() != 0.0 || !(0.0..=MAX_EXPANDED_POWER as f64).contains(&exponent) {
+                return None;
+            }
+            let base = polynomial(u, var, constant)?;
+            let mut result = vec![1.0];
+            for _ in 0..exponent as u32 {
+                result = multiply(&result, &base);
+            }
+            result
+        }
+        _ => return None,
+    };
+
+    while coefficients.len() > 1 && coefficients.last() == Some(&0.0) {
+        coefficients.pop();
+    }
+    Some(coefficients)
+}
+
+/// Whether `var` appears anywhere in `node`
+pub fn depends(node: &Node, var: &str) -> bool {
+    let mut found = false;
+    let _ = node.map_children(|child| {
+        found = found || depends(child, var);
+        child.clone()
+    });
+    found || matches!(&node.ty, NodeType::Identifier(name) if **name == *var)
+}
+
+fn add(a: &[f64], b: &[f64]) -> Vec<f64> {
+    (0..a.len().max(b.len()))
+        .map(|i| a.get(i).unwrap_or(&0.0) + b.get(i).unwrap_or(&0.0))
+        .collect()
+}
+
+fn scale(a: &[f64], factor: f64) -> Vec<f64> {
+    a.iter().map(|x| x * factor).collect()
+}
+
+fn multiply(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let mut result = vec![0.0; a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            result[i + j] += x * y;
+        }
+    }
+    result
+}
+
+fn evaluate(coefficients: &[f64], x: f64) -> f64 {
+    coefficients.iter().rev().fold(0.0, |sum, c| sum * x + c)
+}
+
+/// Every root of a polynomial, as real numbers where they're real and complex numbers
+/// otherwise. Polynomials up to cubics are solved in closed form, and higher degrees
+/// numerically.
+pub fn polynomial_roots(coefficients: &[f64]) -> Vec<Value> {
+    let roots = match *coefficients {
+        [_] => vec![],
+        [c, b] => vec![Complex::from(-c / b)],
+        [c, b, a] => quadratic(a, b, c),
+        [d, c, b, a] => cubic(a, b, c, d),
+        _ => durand_kerner(coefficients),
+    };
+
+    let mut real: Vec<f64> = vec![];
+    let mut complex: Vec<Complex> = vec![];
+    for root in roots {
+        if root.im.abs() <= 1e-12 * root.re.abs().max(1.0) {
+            real.push(polish(coefficients, root.re));
+        } else {
+            complex.push(root);
+        }
+    }
+
+    real.sort_by(f64::total_cmp);
+    real.dedup_by(|a, b| (*a - *b).abs() <= 1e-9 * a.abs().max(1.0));
+    complex.sort_by(|a, b| a.re.total_cmp(&b.re).then(a.im.total_cmp(&b.im)));
+    real.into_iter()
+        .map(Value::Number)
+        .chain(complex.into_iter().map(Value::Complex))
+        .collect()
+}
+
+fn quadratic(a: f64, b: f64, c: f64) -> Vec<Complex> {
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        let re = -b / (2.0 * a);
+        let im = (-discriminant).sqrt() / (2.0 * a);
+        return vec![Complex::new(re, -im.abs()), Complex::new(re, im.abs())];
+    }
+
+    // Avoids cancellation between -b and the square root
+    let q = -(b + b.signum() * discriminant.sqrt()) / 2.0;
+    if q == 0.0 {
+        return vec![Complex::from(0.0)];
+    }
+    vec![Complex::from(q / a), Complex::from(c / q)]
+}
+
+/// Cardano's method, or the trigonometric form when there are three real roots
+fn cubic(a: f64, b: f64, c: f64, d: f64) -> Vec<Complex> {
+    let (b, c, d) = (b / a, c / a, d / a);
+    // x = t - b/3 gives t³ + pt + q = 0
+    let shift = -b / 3.0;
+    let p = c - b * b / 3.0;
+    let q = 2.0 * b * b * b / 27.0 - b * c / 3.0 + d;
+    let discriminant = (q / 2.0).powi(2) + (p / 3.0).powi(3);
+
+    let roots = if p == 0.0 && q == 0.0 {
+        vec![Complex::from(0.0)]
+    } else if discriminant > 0.0 {
+        let u = (-q / 2.0 + discriminant.sqrt()).cbrt();
+        let v = (-q / 2.0 - discriminant.sqrt()).cbrt();
+        let re = -(u + v) / 2.0;
+        let im = 3.0_f64.sqrt() / 2.0 * (u - v);
+        vec![
+            Complex::from(u + v),
+            Complex::new(re, -im.abs()),
+            Complex::new(re, im.abs()),
+        ]
+    } else {
+        let r = 2.0 * (-p / 3.0).sqrt();
+        let theta = ((3.0 * q / (p * r)).clamp(-1.0, 1.0)).acos() / 3.0;
+        (0..3)
+            .map(|k| Complex::from(r * (theta - 2.0 * PI * k as f64 / 3.0).cos()))
+            .collect()
+    };
+    roots
+        .into_iter()
+        .map(|t| t + Complex::from(shift))
+        .collect()
+}
+
+/// All the roots of a polynomial at once with the Durand–Kerner method, which refines a guess
+/// for each root against the others
+fn durand_kerner(coefficients: &[f64]) -> Vec<Complex> {
+    let degree = coefficients.len() - 1;
+    let leading = coefficients[degree];
+    let monic: Vec<Complex> = coefficients
+        .iter()
+        .map(|&c| Complex::from(c / leading))
+        .collect();
+
+    // Every root is within this radius, and starting off the real axis and off symmetric
+    // angles keeps conjugate pairs from starting out equal
+    let radius = 1.0 + monic[..degree].iter().map(|c| c.abs()).fold(0.0, f64::max);
+    let mut roots: Vec<Complex> = (0..degree)
+        .map(|k| Complex::from_polar(radius, 2.0 * PI * k as f64 / degree as f64 + 0.4))
+        .collect();
+    for _ in 0..MAX_POLYNOMIAL_ITERATIONS {
+        let mut converged = true;
+        for i in 0..degree {
+            let mut denominator = Complex::from(1.0);
+            for j in 0..degree {
+                if i != j {
+                    denominator = denominator * (roots[i] - roots[j]);
+                }
+            }
+            let step = evaluate_complex(&monic, roots[i]) / denominator;
+            if !step.re.is_finite() || !step.im.is_finite() {
+                continue;
+            }
+            roots[i] = roots[i] - step;
+            converged &= step.abs() <= f64::EPSILON * roots[i].abs().max(1.0);
+        }
+        if converged {
+            break;
+        }
+    }
+
+    // A root repeated m times only comes out to about the mth root of the precision, as m
+    // guesses spread around it. It's a single root of the (m - 1)th derivative though, so
+    // Newton's method on that from their average finds it precisely.
+    let mut merged = vec![];
+    while let Some(root) = roots.pop() {
+        let tolerance = 1e-3 * root.abs().max(1.0);
+        let (cluster, rest): (Vec<Complex>, Vec<Complex>) = roots
+            .into_iter()
+            .partition(|other| (*other - root).abs() <= tolerance);
+        roots = rest;
+        let mut derivative = monic.clone();
+        for _ in 0..cluster.len() {
+            derivative = differentiate(&derivative);
+        }
+        let slope = differentiate(&derivative);
+        let sum = cluster.iter().fold(root, |sum, &other| sum + other);
+        let mut x = sum.scale(1.0 / (cluster.len() + 1) as f64);
+        for _ in 0..MAX_ITERATIONS {
+            let step = evaluate_complex(&derivative, x) / evaluate_complex(&slope, x);
+            if !step.re.is_finite() || !step.im.is_finite() {
+                break;
+            }
+            x = x - step;
+            if step.abs() <= f64::EPSILON * x.abs().max(1.0) {
+                break;
+            }
+        }
+        // Parts that are only rounding error, like the real part of ±i, are dropped
+        let size = x.abs();
+        let clean = |part: f64| {
+            if part.abs() <= 1e-12 * size {
+                0.0
+            } else {
+                part
+            }
+        };
+        merged.push(Complex::new(clean(x.re), clean(x.im)));
+    }
+
+    // The coefficients are real, so complex roots come in conjugate pairs, which are made exact
+    merged.retain(|root| root.im >= 0.0);
+    let conjugates: Vec<Complex> = merged
+        .iter()
+        .filter(|root| root.im > 0.0)
+        .map(|root| root.conj())
+        .collect();
+    merged.extend(conjugates);
+    merged
+}
+
+fn evaluate_complex(coefficients: &[Complex], z: Complex) -> Complex {
+    coefficients
+        .iter()
+        .rev()
+        .fold(Complex::from(0.0), |sum, &c| sum * z + c)
+}
+
+fn differentiate(coefficients: &[Complex]) -> Vec<Complex> {
+    coefficients
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, c)| c.scale(i as f64))
+        .collect()
+}
+
+/// A couple of Newton steps to clean up rounding error, snapping to an integer when that's
+/// an exact root
+fn polish(coefficients: &[f64], mut x: f64) -> f64 {
+    let derivative: Vec<f64> = coefficients
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, c)| i as f64 * c)
+        .collect();
+    for _ in 0..3 {
+        let slope = evaluate(&derivative, x);
+        if slope == 0.0 {
+            break;
+        }
+        let next = x - evaluate(coefficients, x) / slope;
+        if !next.is_finite() || evaluate(coefficients, next).abs() > evaluate(coefficients, x).abs()
+        {
+            break;
+        }
+        x = next;
+    }
+    if evaluate(coefficients, x.round()) == 0.0 {
+        x.round()
+    } else {
+        x
+    }
+}
+
+/// What a numerical search for roots found
+pub enum Roots {
+    /// Separate roots, in increasing order
+    Points(Vec<f64>),
+    /// An interval where `f` is zero everywhere, whose bounds are `None` where it reaches the
+    /// end of the search
+    Interval(Option<f64>, Option<f64>),
+}
+
+/// Finds real roots of `f` numerically, by scanning for sign changes and refining them with
+/// Brent's method
+pub fn find_roots<E>(mut f: impl FnMut(f64) -> Result<f64, E>) -> Result<Roots, E> {
+    let step = 2.0 * SCAN_RANGE / SCAN_STEPS as f64;
+    let xs: Vec<f64> = (0..=SCAN_STEPS)
+        .map(|i| -SCAN_RANGE + i as f64 * step)
+        .collect();
+    let ys = xs.iter().map(|&x| f(x)).collect::<Result<Vec<_>, _>>()?;
+
+    if let Some(interval) = zero_interval(&mut f, &xs, &ys)? {
+        return Ok(interval);
+    }
+
+    let mut roots: Vec<f64> = vec![];
+    for i in 0..SCAN_STEPS {
+        let (a, b) = (xs[i], xs[i + 1]);
+        let (fa, fb) = (ys[i], ys[i + 1]);
+        if fa == 0.0 {
+            roots.push(a);
+        } else if fa.is_finite() && fb.is_finite() && fa.signum() != fb.signum() && fb != 0.0 {
+            // Poles like tan's change sign too, but don't get close to zero
+            let root = brent(&mut f, a, b, fa, fb)?;
+            if f(root)?.abs() <= 1e-6 * fa.abs().min(fb.abs()).max(1.0) {
+                roots.push(root);
+            }
+        } else if i > 0 && fa.abs() < ys[i - 1].abs() && fa.abs() < fb.abs() {
+            // A local minimum of |f| might be a root that touches zero without crossing it
+            if let Some(root) = newton(&mut f, a)? {
+                if (root - a).abs() <= step {
+                    roots.push(root);
+                }
+            }
+        }
+    }
+    if ys[SCAN_STEPS] == 0.0 {
+        roots.push(xs[SCAN_STEPS]);
+    }
+
+    roots.sort_by(f64::total_cmp);
+    roots.dedup_by(|a, b| (*a - *b).abs() <= 1e-9 * a.abs().max(1.0));
+    Ok(Roots::Points(roots))
+}
+
+/// The first interval where `f` is zero, found from a run of zero samples that `f` is also zero
+/// between. A single undefined sample in the run, like `x/x - 1` at 0, doesn't end it.
+fn zero_interval<E>(
+    f: &mut impl FnMut(f64) -> Result<f64, E>,
+    xs: &[f64],
+    ys: &[f64],
+) -> Result<Option<Roots>, E> {
+    let last = ys.len() - 1;
+    let zero = |i: usize| {
+        ys[i] == 0.0
+            || !ys[i].is_finite() && i > 0 && i < last && ys[i - 1] == 0.0 && ys[i + 1] == 0.0
+    };
+
+    let mut start = 0;
+    while start < last {
+        if !(zero(start) && zero(start + 1)) {
+            start += 1;
+            continue;
+        }
+        let mut end = start + 1;
+        while end < last && zero(end + 1) {
+            end += 1;
+        }
+
+        let mut between = true;
+        for i in start..end {
+            let y = f((xs[i] + xs[i + 1]) / 2.0)?;
+            if y != 0.0 && (y.is_finite() || ys[i].is_finite() && ys[i + 1].is_finite()) {
+                between = false;
+                break;
+            }
+        }
+        if between {
+            let lower = match start {
+                0 => None,
+                _ => Some(edge(f, xs[start], xs[start - 1])?),
+            };
+            let upper = match end {
+                end if end == last => None,
+                _ => Some(edge(f, xs[end], xs[end + 1])?),
+            };
+            return Ok(Some(Roots::Interval(lower, upper)));
+        }
+        start = end + 1;
+    }
+    Ok(None)
+}
+
+/// Where `f` stops being zero between `zero`, where it is, and `other`, where it isn't
+fn edge<E>(
+    f: &mut impl FnMut(f64) -> Result<f64, E>,
+    mut zero: f64,
+    mut other: f64,
+) -> Result<f64, E> {
+    for _ in 0..MAX_ITERATIONS {
+        let middle = (zero + other) / 2.0;
+        if middle == zero || middle == other {
+            break;
+        }
+        if f(middle)? == 0.0 {
+            zero = middle;
+        } else {
+            other = middle;
+        }
+    }
+    // Bisection gets within rounding error of an edge like 1, without landing on it
+    let rounded = (zero * 1e9).round() / 1e9;
+    Ok(if (rounded - zero).abs() <= 1e-12 * zero.abs().max(1.0) {
+        rounded
+    } else {
+        zero
+    })
+}
+
+/// Newton's method from `guess`, for a single root near it
+pub fn newton<E>(f: &mut impl FnMut(f64) -> Result<f64, E>, guess: f64) -> Result<Option<f64>, E> {
+    let mut x = guess;
+    for _ in 0..MAX_ITERATIONS {
+        let y = f(x)?;
+        if y == 0.0 {
+            return Ok(Some(x));
+        }
+        let h = 1e-7 * x.abs().max(1.0);
+        let slope = (f(x + h)? - f(x - h)?) / (2.0 * h);
+        if slope == 0.0 || !slope.is_finite() {
+            break;
+        }
+        let next = x - y / slope;
+        if !next.is_finite() {
+            break;
+        }
+        if (next - x).abs() <= 1e-14 * next.abs().max(1.0) {
+            x = next;
+            break;
+        }
+        x = next;
+    }
+
+    let y = f(x)?;
+    Ok((y.abs() <= 1e-10 * x.abs().max(1.0)).then_some(x))
+}
+
+/// Brent's method on a bracket `[a, b]` where `f` changes sign
+fn brent<E>(
+    f: &mut impl FnMut(f64) -> Result<f64, E>,
+    mut a: f64,
+    mut b: f64,
+    mut fa: f64,
+    mut fb: f64,
+) -> Result<f64, E> {
+    if fa.abs() < fb.abs() {
+        (a, b, fa, fb) = (b, a, fb, fa);
+    }
+    let (mut c, mut fc) = (a, fa);
+    let mut d = b - a;
+    let mut bisected = true;
+
+    for _ in 0..MAX_ITERATIONS {
+        if fb == 0.0 || (b - a).abs() <= 1e-15 * b.abs().max(1.0) {
+            break;
+        }
+
+        let mut s = if fa != fc && fb != fc {
+            // Inverse quadratic interpolation
+            a * fb * fc / ((fa - fb) * (fa - fc))
+                + b * fa * fc / ((fb - fa) * (fb - fc))
+                + c * fa * fb / ((fc - fa) * (fc - fb))
+        } else {
+            // Secant
+            b - fb * (b - a) / (fb - fa)
+        };
+
+        let between = (s - (3.0 * a + b) / 4.0) * (s - b) < 0.0;
+        let slow = if bisected {
+            (s - b).abs() >= (b - c).abs() / 2.0
+        } else {
+            (s - b).abs() >= (c - d).abs() / 2.0
+        };
+        bisected = !between || slow;
+        if bisected {
+            s = (a + b) / 2.0;
+        }
+
+        let fs = f(s)?;
+        d = c;
+        (c, fc) = (b, fb);
+        if fa * fs < 0.0 {
+            (b, fb) = (s, fs);
+        } else {
+            (a, fa) = (s, fs);
+        }
+        if fa.abs() < fb.abs() {
+            (a, b, fa, fb) = (b, a, fb, fa);
+        }
+    }
+    Ok(b)
+}
+
+/// Replaces calls to user functions with their bodies, so `f(x) = 0` can be read as a
+/// polynomial when `f` is one
+pub fn inline(node: &Node, scope: &Scope, depth: u32) -> Node {
+    if depth == MAX_INLINE_DEPTH {
+        return node.clone();
+    }
+    if let NodeType::Call(name, args) = &node.ty {
+        if let Some(Value::Function(Function {
+            arg_names, body, ..
+        })) = scope.get(name)
+        {
+            if arg_names.len() == args.len() {
+                let bindings = arg_names.into_iter().zip(args.iter().cloned()).collect();
+                return inline(&substitute(&body, &bindings), scope, depth + 1);
+            }
+        }
+    }
+    if let NodeType::Block(statements) = &node.ty {
+        if let Some(flat) = flatten(statements) {
+            return inline(&flat, scope, depth);
+        }
+    }
+    node.map_children(|child| inline(child, scope, depth))
+}
@@ -1,11 +1,26 @@
 use std::{fmt, ops::Range, rc::Rc};
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
     Number(Rc<str>),
     Identifier(Rc<str>),
+    Bool(bool),
     Superscript(Vec<Token>),
+    Subscript(Vec<Token>),
     Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+    Not,
+    If,
+    Then,
+    Else,
+    Otherwise,
     Plus,
     Minus,
     Star,
@@ -19,18 +34,28 @@ pub enum TokenType {
     Sqrt,
     Cbrt,
     Fort,
+    Sum,
+    Product,
+    Integral,
+    Arrow,
+    Compose,
     Degree,
     Exclamation,
     LeftParen,
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    LeftAngle,
+    RightAngle,
     Pipe,
     LeftFloor,
     RightFloor,
     LeftCeil,
     RightCeil,
     Comma,
+    Semicolon,
     Newline,
     EOF,
 }
@@ -40,6 +65,7 @@ impl fmt::Display for TokenType {
         match self {
             Self::Number(value) => write!(f, "{}", value),
             Self::Identifier(name) => write!(f, "{}", name),
+            Self::Bool(value) => write!(f, "{}", value),
             Self::Superscript(tokens) => write!(
                 f,
                 "^({})",
@@ -49,7 +75,28 @@ impl fmt::Display for TokenType {
                     .collect::<Vec<_>>()
                     .join(" ")
             ),
+            Self::Subscript(tokens) => write!(
+                f,
+                "_({})",
+                tokens
+                    .iter()
+                    .map(|t| t.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
             Self::Eq => write!(f, "'='"),
+            Self::Ne => write!(f, "'≠'"),
+            Self::Lt => write!(f, "'<'"),
+            Self::Le => write!(f, "'≤'"),
+            Self::Gt => write!(f, "'>'"),
+            Self::Ge => write!(f, "'≥'"),
+            Self::And => write!(f, "'∧'"),
+            Self::Or => write!(f, "'∨'"),
+            Self::Not => write!(f, "'¬'"),
+            Self::If => write!(f, "'if'"),
+            Self::Then => write!(f, "'then'"),
+            Self::Else => write!(f, "'else'"),
+            Self::Otherwise => write!(f, "'otherwise'"),
             Self::Plus => write!(f, "'+'"),
             Self::Minus => write!(f, "'-'"),
             Self::Star => write!(f, "'*'"),
@@ -63,18 +110,28 @@ impl fmt::Display for TokenType {
             Self::Sqrt => write!(f, "'√'"),
             Self::Cbrt => write!(f, "'∛'"),
             Self::Fort => write!(f, "'∜'"),
+            Self::Sum => write!(f, "'Σ'"),
+            Self::Product => write!(f, "'Π'"),
+            Self::Integral => write!(f, "'∫'"),
+            Self::Arrow => write!(f, "'↦'"),
+            Self::Compose => write!(f, "'∘'"),
             Self::Degree => write!(f, "'°'"),
             Self::Exclamation => write!(f, "'!'"),
             Self::LeftParen => write!(f, "'('"),
             Self::RightParen => write!(f, "')'"),
             Self::LeftBrace => write!(f, "'{{'"),
             Self::RightBrace => write!(f, "'}}'"),
+            Self::LeftBracket => write!(f, "'['"),
+            Self::RightBracket => write!(f, "']'"),
+            Self::LeftAngle => write!(f, "'⟨'"),
+            Self::RightAngle => write!(f, "'⟩'"),
             Self::Pipe => write!(f, "'|'"),
             Self::LeftFloor => write!(f, "'⌊'"),
             Self::RightFloor => write!(f, "'⌋'"),
             Self::LeftCeil => write!(f, "'⌈'"),
             Self::RightCeil => write!(f, "'⌉'"),
             Self::Comma => write!(f, "','"),
+            Self::Semicolon => write!(f, "';'"),
             Self::Newline => write!(f, "'\\n'"),
             Self::EOF => write!(f, "<eof>"),
         }
@@ -85,6 +142,19 @@ impl fmt::Display for TokenType {
 pub struct Token {
     pub ty: TokenType,
     pub range: Range<usize>,
+    /// The comments between the previous token and this one. They don't mean anything to the
+    /// parser, but keeping them lets the source be put back together from its tokens.
+    pub comments: Vec<Comment>,
+}
+
+impl Token {
+    pub fn new(ty: TokenType, range: Range<usize>) -> Self {
+        Self {
+            ty,
+            range,
+            comments: vec![],
+        }
+    }
 }
 
 impl fmt::Display for Token {
@@ -92,3 +162,11 @@ impl fmt::Display for Token {
         write!(f, "{}", self.ty)
     }
 }
+
+/// A `#` or `//` comment running to the end of the line, or a `/* */` block comment
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    /// The whole comment, including the characters that start and end it
+    pub text: Rc<str>,
+    pub range: Range<usize>,
+}
@@ -1,21 +1,65 @@
 use std::{fmt, rc::Rc};
 
-use crate::Node;
+use crate::{
+    simplify::simplify, BigInt, Captured, Complex, Matrix, NativeFunction, Node, Rational,
+};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub enum Value {
     Number(f64),
-    Function {
-        name: Rc<str>,
-        arg_names: Vec<Rc<str>>,
-        body: Box<Node>,
-    },
-    NativeFunction(fn(&Vec<Value>) -> Result<Value, String>),
+    Integer(BigInt),
+    Rational(Rational),
+    Complex(Complex),
+    Bool(bool),
+    Vector(Vec<Value>),
+    Matrix(Matrix),
+    Function(Function),
+    NativeFunction(NativeFunction),
+}
+
+/// A function written in ami, like `f(x) = x²` or `x ↦ x²`
+#[derive(Debug, Clone)]
+pub struct Function {
+    /// Empty for an anonymous function
+    pub name: Rc<str>,
+    pub arg_names: Vec<Rc<str>>,
+    pub(crate) body: Box<Node>,
+    /// Where the function was defined, so its body can use the variables around it
+    pub(crate) scope: Captured,
+}
+
+impl Value {
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(x) => Some(*x),
+            Self::Integer(n) => Some(n.to_f64()),
+            Self::Rational(r) => Some(r.to_f64()),
+            _ => None,
+        }
+    }
 }
 
 impl From<i32> for Value {
     fn from(value: i32) -> Self {
-        Self::Number(value as f64)
+        Self::Integer(value.into())
+    }
+}
+
+impl From<BigInt> for Value {
+    fn from(value: BigInt) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<Complex> for Value {
+    fn from(value: Complex) -> Self {
+        Self::Complex(value)
     }
 }
 
@@ -29,12 +73,35 @@ impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Number(value) => write!(f, "{}", value),
-            Self::Function {
-                name,
-                arg_names: _,
-                body: _,
-            } => write!(f, "<fn {}>", name),
-            Self::NativeFunction(_) => write!(f, "<native fn>"),
+            Self::Integer(value) => write!(f, "{}", value),
+            Self::Rational(value) => write!(f, "{}", value),
+            Self::Complex(value) => value.fmt(f),
+            Self::Bool(value) => write!(f, "{}", value),
+            Self::Matrix(matrix) => write!(f, "{}", matrix),
+            Self::Vector(elements) => {
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    element.fmt(f)?;
+                }
+                write!(f, "]")
+            }
+            Self::Function(function) => function.fmt(f),
+            Self::NativeFunction(function) => write!(f, "<native fn {}>", function.name),
+        }
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let body = simplify(&self.body);
+        let args = self.arg_names.join(", ");
+        match (self.name.is_empty(), self.arg_names.as_slice()) {
+            (true, [arg]) => write!(f, "{} ↦ {:#}", arg, body),
+            (true, _) => write!(f, "({}) ↦ {:#}", args, body),
+            (false, _) => write!(f, "{}({}) = {:#}", self.name, args, body),
         }
     }
 }
@@ -0,0 +1,49 @@
+fn run(source: &str) -> String {
+    ami::eval(source).unwrap().to_string()
+}
+
+fn polar(source: &str) -> String {
+    format!("{:#}", ami::eval(source).unwrap())
+}
+
+#[test]
+fn square_roots_of_negative_numbers() {
+    assert_eq!(run("sqrt(-1)"), "i");
+    assert_eq!(run("√(-4)"), "2i");
+    assert_eq!(run("i^2"), "-1");
+    assert_eq!(run("1/i"), "-i");
+    assert_eq!(run("ln(-1)"), "3.141592653589793i");
+}
+
+#[test]
+fn arithmetic() {
+    assert_eq!(run("(1+i)^2"), "2i");
+    assert_eq!(run("(2+3i)/(1-i)"), "-0.5 + 2.5i");
+    assert_eq!(run("abs(3+4i)"), "5");
+}
+
+#[test]
+fn eulers_identity() {
+    assert_eq!(run("re(e^(i*π))"), "-1");
+    assert_eq!(run("abs(im(e^(i*π))) < 1e-15"), "true");
+    assert_eq!(run("abs(e^(i*π/2) - i) < 1e-15"), "true");
+}
+
+#[test]
+fn parts() {
+    assert_eq!(run("re(3+4i)"), "3");
+    assert_eq!(run("im(3+4i)"), "4");
+    assert_eq!(run("conj(3+4i)"), "3 - 4i");
+    assert_eq!(run("arg(i)"), run("π/2"));
+    assert_eq!(run("arg(-1)"), run("π"));
+}
+
+#[test]
+fn polar_form() {
+    assert_eq!(polar("sqrt(-1)"), "1∠1.5707963267948966");
+    assert_eq!(polar("conj(3+4i)"), "5∠-0.9272952180016122");
+    assert_eq!(polar("e^(i*π)"), "1∠3.141592653589793");
+    // Real numbers look the same either way
+    assert_eq!(polar("i^2"), "-1");
+    assert_eq!(run("abs(polar(2, π/2) - 2i) < 1e-15"), "true");
+}
@@ -0,0 +1,59 @@
+fn run(source: &str) -> String {
+    match ami::eval(source) {
+        Ok(value) => value.to_string(),
+        Err(error) => error.reason,
+    }
+}
+
+#[test]
+fn d_over_a_differential_only_differentiates_an_operand() {
+    assert_eq!(run("d = 6\ndt = 2\nd/dt"), "3");
+    assert_eq!(run("d = 6\ndt = 2\nd/dt * 5"), "15");
+    assert_eq!(run("d/dt t²"), "t ↦ 2t");
+}
+
+#[test]
+fn functions_are_differentiated_by_name() {
+    let f = "f(x) = x³ + 2x\n";
+    assert_eq!(run(&format!("{}derive(f, x)", f)), "f'(x) = 3x^2 + 2");
+    assert_eq!(run(&format!("{}f'", f)), "f'(x) = 3x^2 + 2");
+    assert_eq!(run(&format!("{}f''", f)), "f''(x) = 6x");
+    assert_eq!(run(&format!("{}f'(2)", f)), "14");
+    assert_eq!(run(&format!("{}f''(2)", f)), "12");
+    assert_eq!(run("sin'"), "sin'(x) = cos(x)");
+    assert_eq!(run("g(x, a) = a * x²\nderive(g, a)"), "g'(x, a) = x^2");
+    assert_eq!(run("g(x) = x\nderive(g, t)"), "'t' is not an argument of g");
+}
+
+#[test]
+fn the_rules_of_differentiation() {
+    // Sum, product, quotient and chain rules
+    assert_eq!(run("d/dx (x² + 3x)"), "x ↦ 2x + 3");
+    assert_eq!(run("d/dx (x * sin(x))"), "x ↦ sin(x) + x × cos(x)");
+    assert_eq!(run("d/dx (sin(x) / x)"), "x ↦ (cos(x) × x - sin(x)) ÷ x^2");
+    assert_eq!(run("d/dx sin(x²)"), "x ↦ 2cos(x^2) × x");
+    assert_eq!(run("d/dx exp(2x)"), "x ↦ 2exp(2x)");
+    assert_eq!(run("f(x) = sin(x) * cos(x)\nf'(0)"), "1");
+    assert_eq!(run("g(x) = sin(x)/x\ng'(π)"), "-0.3183098861837907");
+    assert_eq!(run("h(x) = e^(x²)\nh'(1)"), "5.43656365691809");
+}
+
+#[test]
+fn built_in_functions() {
+    assert_eq!(run("d/dx ln(x)"), "x ↦ 1 ÷ x");
+    assert_eq!(run("d/dx sqrt(x)"), "x ↦ 1 ÷ 2sqrt(x)");
+    assert_eq!(run("d/dx tan(x)"), "x ↦ 1 ÷ cos(x)^2");
+    assert_eq!(run("d/dx atan(x)"), "x ↦ 1 ÷ (x^2 + 1)");
+    assert_eq!(run("d/dx cosh(x)"), "x ↦ sinh(x)");
+    assert_eq!(run("sin'(0)"), "1");
+    assert_eq!(run("d/dx beta(x, 1)"), "cannot differentiate beta(x, 1)");
+}
+
+#[test]
+fn double_factorials_have_no_derivative() {
+    assert_eq!(
+        run("d/dx (x!!)"),
+        "(x!!) is only defined on integers, so it has no derivative"
+    );
+    assert_eq!(run("d/dx (x!)"), "x ↦ x! × digamma(x + 1)");
+}
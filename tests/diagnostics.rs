@@ -0,0 +1,112 @@
+fn render(source: &str) -> String {
+    let error = ami::eval(source).unwrap_err();
+    error.render(source, false)
+}
+
+/// The text the error's label underlines, read off the rendered diagnostic
+fn underlined(source: &str) -> String {
+    let rendered = render(source);
+    let lines: Vec<Vec<char>> = rendered
+        .lines()
+        .map(|line| line.chars().collect())
+        .collect();
+    let row = lines
+        .iter()
+        .position(|line| line.get(1).is_some_and(char::is_ascii_digit))
+        .expect("no source line in the diagnostic");
+    let margin = lines[row].iter().position(|&ch| ch == '│').unwrap() + 2;
+
+    lines[row + 1]
+        .iter()
+        .enumerate()
+        .skip(margin)
+        .filter(|(_, ch)| matches!(ch, '─' | '┬'))
+        .filter_map(|(i, _)| lines[row].get(i))
+        .collect()
+}
+
+#[test]
+fn ranges_count_bytes_after_multibyte_characters() {
+    assert_eq!(underlined("y = αβ + 1"), "αβ");
+    assert_eq!(underlined("√2 + π + ¤"), "¤");
+    assert_eq!(underlined("g′(1)"), "g′");
+}
+
+#[test]
+fn ranges_inside_superscripts_point_at_the_source() {
+    assert_eq!(underlined("2ˣ⁺¹"), "ˣ");
+    assert_eq!(underlined("a = 1\n3ᵃ⁺ᵇ"), "ᵇ");
+}
+
+#[test]
+fn ranges_on_later_lines() {
+    let source = "a = 1\nb = a +\nc = a + d";
+    assert!(render(source).contains("<unknown>:2:"));
+    assert_eq!(underlined("a = 1\nb = 2\nc = a + d"), "d");
+    assert!(render("a = 1\nb = 2\nc = a + d").contains(" 3 │ c = a + d"));
+}
+
+#[test]
+fn argument_errors_point_at_the_argument() {
+    assert_eq!(underlined("x = 2\nsin(x) + det([1, 2])"), "[1, 2]");
+    assert_eq!(underlined("max(1, 2, ⟨1, 2⟩)"), "⟨1, 2⟩");
+}
+
+#[test]
+fn malformed_numbers_point_at_the_problem() {
+    assert_eq!(underlined("1 + 1.2.3"), "1.2.3");
+    assert_eq!(underlined("0b1021"), "2");
+    assert_eq!(underlined("0xfg"), "g");
+    assert_eq!(underlined("0xg"), "g");
+    assert_eq!(underlined("1 + 0b"), "0b");
+    assert!(render("0xg").contains("expected a hexadecimal digit"));
+    assert!(render("2 * 0o").contains("expected an octal digit"));
+    assert_eq!(underlined("1__000"), "_");
+    assert_eq!(underlined("1e2.5"), ".");
+    assert!(render("1.2.3").contains("a number can only have one decimal point"));
+}
+
+#[test]
+fn unterminated_comments_point_at_the_opening() {
+    assert_eq!(underlined("x = 1 /* never /* closed */"), "/*");
+}
+
+#[test]
+fn undefined_names_suggest_close_matches() {
+    let reason = |source: &str| ami::eval(source).unwrap_err().reason;
+    assert_eq!(
+        reason("sinn(1)"),
+        "'sinn' is not defined, did you mean 'sin'?"
+    );
+    assert_eq!(
+        reason("total = 1\ntotl"),
+        "'totl' is not defined, did you mean 'total'?"
+    );
+    assert_eq!(reason("sn(1)"), "'sn' is not defined, did you mean 'ln'?");
+    // Any other single letter is one edit away, so one letter names get no suggestion
+    assert_eq!(reason("y + 1"), "'y' is not defined");
+    assert_eq!(reason("frobnicate"), "'frobnicate' is not defined");
+}
+
+#[test]
+fn derivatives_of_undefined_names_are_errors() {
+    let error = |source: &str| ami::eval(source).unwrap_err();
+    let undefined = error("speed(t) = 3t²\nderive(sped, t)");
+    assert_eq!(undefined.msg, "undefined name 'sped'");
+    assert_eq!(
+        undefined.reason,
+        "'sped' is not defined, did you mean 'speed'?"
+    );
+    assert_eq!(error("d/dx (x + y)").reason, "'y' is not defined");
+    assert_eq!(error("f(x) = x + a\nf'(1)").reason, "'a' is not defined");
+    assert_eq!(
+        error("d/dx (x + sin)").reason,
+        "sin is a function, so it needs an argument, like sin(x)"
+    );
+
+    // Other values, arguments and indices are constants
+    let run = |source: &str| ami::eval(source).unwrap().to_string();
+    assert_eq!(run("y = 3\nd/dx (x * y)"), "x ↦ y");
+    assert_eq!(run("g(x, a) = a * x²\nderive(g, x)"), "g'(x, a) = 2a × x");
+    assert_eq!(run("d/dx Σ(k=1, 3, k * x)"), "x ↦ Σ(k = 1, 3, k)");
+}
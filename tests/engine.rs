@@ -0,0 +1,302 @@
+use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+};
+
+use ami::{BigInt, Complex, Diagnostic, Engine, NativeFunction, Value};
+
+#[test]
+fn eval_returns_the_last_statement() {
+    let value = ami::eval("x = 3\nx² + 1").unwrap();
+    assert_eq!(value.to_string(), "10");
+}
+
+#[test]
+fn comments_are_skipped() {
+    let source =
+        "# the radius\nr = 2 // metres\n/* the area,\n   not the circumference */\nπ * r² /* m² */";
+    let value = ami::eval(source).unwrap();
+    assert_eq!(value.as_f64(), Some(4.0 * std::f64::consts::PI));
+}
+
+#[test]
+fn blocks_have_their_own_scope() {
+    let source = "a = 1\nf(x) = { a = x²; b = a + 1; b / 2 }\nf(3) + a";
+    assert_eq!(ami::eval(source).unwrap().to_string(), "6");
+    assert_eq!(ami::eval("x = 2; { x = 5 }; x").unwrap().to_string(), "2");
+    assert!(ami::eval("1 2").is_err());
+}
+
+#[test]
+fn constants_have_ascii_names() {
+    assert_eq!(ami::eval("𝜑 - phi").unwrap().to_string(), "0");
+    assert_eq!(ami::eval("2pi = tau").unwrap().to_string(), "true");
+    assert_eq!(ami::eval("1 / inf").unwrap().to_string(), "0");
+}
+
+#[test]
+fn engine_keeps_state_between_runs() {
+    let mut engine = Engine::new();
+    engine.eval("f(x) = 2x + 1").unwrap();
+    assert_eq!(engine.eval("f(4)").unwrap().to_string(), "9");
+}
+
+#[test]
+fn host_variables_can_be_set_and_read_back() {
+    let mut engine = Engine::new();
+    engine.set("rate", 0.5);
+    engine.eval("total = 10 rate").unwrap();
+    assert_eq!(engine.get("total").and_then(|v| v.as_f64()), Some(5.0));
+    assert!(engine.get("missing").is_none());
+}
+
+#[test]
+fn host_functions_can_be_registered() {
+    let mut engine = Engine::new();
+    engine.register("double", 1..=1, |_, args| match args[0].as_f64() {
+        Some(x) => Ok(Value::Number(2.0 * x)),
+        None => Err(args.error(0, "expected a number".to_string())),
+    });
+    assert_eq!(engine.eval("double(21)").unwrap().to_string(), "42");
+
+    let error = engine.eval("double(1, 2)").unwrap_err();
+    assert_eq!(error.reason, "double takes 1 argument, but was given 2");
+    let error = engine.eval("double(true)").unwrap_err();
+    assert_eq!(error.range, 7..11);
+}
+
+#[test]
+fn hosts_can_read_every_kind_of_value() {
+    let mut engine = Engine::new();
+    engine
+        .eval("f(x, y) = x * y\nn = 20!\nq = 1/3\nz = 1 + 2i\nm = [1, 2; 3, 4]")
+        .unwrap();
+    match engine.get("f") {
+        Some(Value::Function(f)) => {
+            assert_eq!(&*f.name, "f");
+            assert_eq!(f.arg_names.len(), 2);
+        }
+        value => panic!("f is {:?}", value),
+    }
+    match engine.get("n") {
+        Some(Value::Integer(n)) => assert_eq!(n, BigInt::from(2432902008176640000_i64)),
+        value => panic!("n is {:?}", value),
+    }
+    match engine.get("q") {
+        Some(Value::Rational(q)) => assert_eq!((q.numer(), q.denom()), (&1.into(), &3.into())),
+        value => panic!("q is {:?}", value),
+    }
+    match engine.get("z") {
+        Some(Value::Complex(z)) => assert_eq!(z, Complex::new(1.0, 2.0)),
+        value => panic!("z is {:?}", value),
+    }
+    match engine.get("m") {
+        Some(Value::Matrix(m)) => assert_eq!((m.rows(), m.cols(), m[(1, 0)]), (2, 2, 3.0)),
+        value => panic!("m is {:?}", value),
+    }
+}
+
+#[test]
+fn errors_have_a_range_in_the_source() {
+    let error = ami::eval("1 + y").unwrap_err();
+    assert_eq!(error.range, 4..5);
+}
+
+#[test]
+fn runaway_recursion_is_an_error() {
+    let error = ami::eval("f(x) = f(x)\nf(1)").unwrap_err();
+    assert_eq!(error.msg, "maximum recursion depth exceeded");
+    assert_eq!(error.range, 7..11);
+
+    let fact = "fact(n) = if n ≤ 1 then 1 else n * fact(n - 1)";
+    let value = ami::eval(&format!("{}\nfact(3000) / fact(2999)", fact)).unwrap();
+    assert_eq!(value.to_string(), "3000");
+}
+
+#[test]
+fn calls_with_the_wrong_number_of_arguments_are_errors() {
+    let error = ami::eval("sq = x ↦ x²\nsq(1, 2)").unwrap_err();
+    assert_eq!(error.reason, "sq takes 1 argument, but was given 2");
+    assert_eq!(error.range, 15..23);
+
+    let error = ami::eval("f(x, y) = x + y\nf(1)").unwrap_err();
+    assert_eq!(error.reason, "f takes 2 arguments, but was given 1");
+    assert_eq!(error.range, 16..20);
+}
+
+#[test]
+fn slowly_converging_series_are_extrapolated() {
+    let close = |source: &str, expected: f64| {
+        let value = ami::eval(source).unwrap().as_f64().unwrap();
+        assert!((value - expected).abs() < 1e-11, "{} = {}", source, value);
+    };
+    close("Σ(k=1,∞,1/k²)", std::f64::consts::PI.powi(2) / 6.0);
+    close("Σ(k=1,∞,(-1)^(k+1)/k)", std::f64::consts::LN_2);
+    close("Σ(k=0,∞,1/k!)", std::f64::consts::E);
+    close(
+        "∏(k=1,∞,1 + 1/k²)",
+        std::f64::consts::PI.sinh() / std::f64::consts::PI,
+    );
+    assert_eq!(ami::eval("Σ(k=1,4,1/k)").unwrap().to_string(), "25/12");
+}
+
+#[test]
+fn divergent_series_are_given_up_on_early() {
+    let reason = |source: &str| ami::eval(source).unwrap_err().reason;
+    assert_eq!(reason("Σ(k=1,∞,1/k)"), "still changing after 16384 terms");
+    assert_eq!(
+        reason("Σ(k=1,∞,sin(k))"),
+        "still changing after 16384 terms"
+    );
+    assert_eq!(
+        reason("∏(k=1,∞,1 + 1/k)"),
+        "still changing after 16384 terms"
+    );
+}
+
+#[test]
+fn diagnostics_go_to_the_callback() {
+    let diagnostics = Rc::new(RefCell::new(vec![]));
+    let mut engine = Engine::new();
+    let sink = diagnostics.clone();
+    engine.on_diagnostic(move |diagnostic| sink.borrow_mut().push(diagnostic.clone()));
+    engine.eval("∫₀^1 x dx").unwrap();
+
+    let diagnostics = diagnostics.borrow();
+    assert!(matches!(diagnostics[0], Diagnostic::Tokens(_)));
+    assert!(matches!(diagnostics[1], Diagnostic::Ast(_)));
+    assert!(matches!(diagnostics[2], Diagnostic::IntegralError(error) if error < 1e-10));
+    assert_eq!(diagnostics.len(), 3);
+}
+
+#[test]
+fn functions_see_the_scope_they_were_defined_in() {
+    let run = |source: &str| ami::eval(source).unwrap().to_string();
+    // The variables around the definition, not the caller's
+    assert_eq!(run("a = 1\nf(x) = x + a\ng(a) = f(0)\ng(100)"), "1");
+    assert_eq!(run("a = 1\nf(x) = x + a\na = 5\nf(0)"), "5");
+    // Closures keep the call they were made in alive
+    assert_eq!(run("adder(n) = (x ↦ x + n)\nadd3 = adder(3)\nadd3(4)"), "7");
+    assert_eq!(
+        run("adder(n) = (x ↦ x + n)\nf = adder(1)\ng = adder(10)\nf(2) + g(2)"),
+        "15"
+    );
+    assert_eq!(
+        run("twice(n) = { step(x) = x + n; both(x) = step(step(x)); both }\nf = twice(5)\nf(1)"),
+        "11"
+    );
+}
+
+#[test]
+fn any_function_value_can_be_called() {
+    let run = |source: &str| match ami::eval(source) {
+        Ok(value) => value.to_string(),
+        Err(error) => error.reason,
+    };
+    assert_eq!(run("f(x) = x + 1\ng(x) = 2x\n(f ∘ g)(3)"), "7");
+    assert_eq!(run("(x ↦ x²)(4)"), "16");
+    assert_eq!(run("adder(n) = (x ↦ x + n)\nadder(2)(3)"), "5");
+    assert_eq!(run("v = [sin, cos]\nv[2](0)"), "1");
+    assert_eq!(run("(d/dx x³)(2)"), "12");
+    assert_eq!(
+        run("(x ↦ x²)(1, 2)"),
+        "x ↦ x^2 takes 1 argument, but was given 2"
+    );
+    assert_eq!(run("(1 + 2)(3)"), "expected a function, found 3");
+}
+
+#[test]
+fn functions_combine_with_operators() {
+    let run = |source: &str| match ami::eval(source) {
+        Ok(value) => value.to_string(),
+        Err(error) => error.reason,
+    };
+    assert_eq!(run("f(x) = x²\ng(x) = sin(x)\nf + g"), "x ↦ f(x) + g(x)");
+    assert_eq!(run("f(x) = x²\n2f"), "x ↦ 2f(x)");
+    // The arguments are passed by position, whatever they're called
+    assert_eq!(run("f(x) = x²\ng(y) = 2y\nh = f + g\nh(3)"), "15");
+    assert_eq!(run("f(x) = x²\nh = f - f\nh(3)"), "0");
+    assert_eq!(run("f(x) = x²\na = 3\nh = a * f\nh(2)"), "12");
+    assert_eq!(run("f(x) = x²\nh = f / 2.5\nh(5)"), "10");
+    // Each body runs in the scope it was defined in
+    assert_eq!(
+        run("f(x) = x²\ng(y) = x * y\nx = 10\nh = f + g\nh(2)"),
+        "24"
+    );
+    assert_eq!(
+        run("adder(n) = (x ↦ x + n)\nh = adder(1) * adder(10)\nh(1)"),
+        "22"
+    );
+    assert_eq!(
+        run("f(x) = x²\ng(x, y) = x + y\nf + g"),
+        "f takes 1 argument, but g takes 2 arguments"
+    );
+}
+
+/// Registers `token()`, which makes a new function that is only kept alive by the scope it's
+/// stored in, along with a list of weak references that show which tokens have been freed
+fn register_tokens(engine: &mut Engine) -> Rc<RefCell<Vec<Weak<()>>>> {
+    let tokens = Rc::new(RefCell::new(vec![]));
+    let made = tokens.clone();
+    engine.register("token", 0..=0, move |_, _| {
+        let token = Rc::new(());
+        made.borrow_mut().push(Rc::downgrade(&token));
+        Ok(Value::NativeFunction(NativeFunction::new(
+            "token",
+            0..=0,
+            move |_, _| Ok(Value::from(Rc::strong_count(&token) as i32)),
+        )))
+    });
+    tokens
+}
+
+#[test]
+fn functions_that_capture_their_own_scope_are_freed() {
+    let mut engine = Engine::new();
+    let tokens = register_tokens(&mut engine);
+    engine.eval("t = token()\nf(x) = x + 1").unwrap();
+    engine
+        .eval("make(n) = { t = token(); g(x) = if x = 0 then n else g(x - 1); g }\nh = make(3)")
+        .unwrap();
+    assert_eq!(engine.eval("h(10)").unwrap().to_string(), "3");
+    drop(engine);
+    assert!(tokens
+        .borrow()
+        .iter()
+        .all(|token| token.upgrade().is_none()));
+
+    // A function taken out of the engine keeps the scopes it needs
+    let mut engine = Engine::new();
+    let tokens = register_tokens(&mut engine);
+    engine
+        .eval("make(n) = { t = token(); g(x) = if x = 0 then n else g(x - 1); g }\nh = make(3)")
+        .unwrap();
+    let h = engine.get("h").unwrap();
+    drop(engine);
+    let mut engine = Engine::new();
+    engine.set("h", h);
+    assert_eq!(engine.eval("h(2)").unwrap().to_string(), "3");
+    drop(engine);
+    assert!(tokens.borrow()[0].upgrade().is_none());
+
+    // A function kept by a host function still sees the call it was made in, which is freed
+    // along with it
+    let mut engine = Engine::new();
+    let tokens = register_tokens(&mut engine);
+    let kept = Rc::new(RefCell::new(vec![]));
+    let keep = kept.clone();
+    engine.register("keep", 1..=1, move |_, args| {
+        keep.borrow_mut().push(args[0].clone());
+        Ok(Value::Bool(true))
+    });
+    engine
+        .eval("f(n) = { t = token(); g(x) = if x = 0 then n else g(x - 1); keep(g); g(2) }")
+        .unwrap();
+    assert_eq!(engine.eval("f(1)").unwrap().to_string(), "1");
+    assert!(tokens.borrow()[0].upgrade().is_some());
+    let mut other = Engine::new();
+    other.set("g", kept.borrow_mut().pop().unwrap());
+    assert_eq!(other.eval("g(5)").unwrap().to_string(), "1");
+    drop(other);
+    assert!(tokens.borrow()[0].upgrade().is_none());
+}
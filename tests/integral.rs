@@ -0,0 +1,64 @@
+use std::cell::RefCell;
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+use ami::{Diagnostic, Engine};
+
+fn close(source: &str, expected: f64) {
+    let value = ami::eval(source).unwrap().as_f64().unwrap();
+    assert!((value - expected).abs() < 1e-9, "{} = {}", source, value);
+}
+
+fn error(source: &str) -> (String, String) {
+    let error = ami::eval(source).unwrap_err();
+    (error.msg, error.reason)
+}
+
+#[test]
+fn definite_integrals() {
+    close("∫(0, π, sin(x), x)", 2.0);
+    close("∫(0, 1, x^2, x)", 1.0 / 3.0);
+    close("∫(1, 2, 1/t, t)", 2f64.ln());
+}
+
+#[test]
+fn the_written_form() {
+    close("∫₀^π sin(x) dx", 2.0);
+    close("∫₀^1 x² dx", 1.0 / 3.0);
+}
+
+#[test]
+fn improper_integrals() {
+    close("∫(0, ∞, e^-x, x)", 1.0);
+    close("∫(-∞, 0, e^x, x)", 1.0);
+    close("∫(-∞, ∞, e^(-x^2), x)", PI.sqrt());
+}
+
+#[test]
+fn divergent_integrals_are_errors() {
+    let (message, reason) = error("∫(1, ∞, 1/x, x)");
+    assert_eq!(message, "integral does not converge");
+    assert_eq!(reason, "it grows without bound");
+}
+
+#[test]
+fn the_error_estimate_is_reported() {
+    let estimates = Rc::new(RefCell::new(vec![]));
+    let mut engine = Engine::new();
+    let sink = estimates.clone();
+    engine.on_diagnostic(move |diagnostic| {
+        if let Diagnostic::IntegralError(error) = diagnostic {
+            sink.borrow_mut().push(*error);
+        }
+    });
+    engine.eval("∫(0, π, sin(x), x)").unwrap();
+    engine.eval("∫(0, ∞, e^-x, x)").unwrap();
+
+    let estimates = estimates.borrow();
+    assert_eq!(estimates.len(), 2);
+    assert!(
+        estimates.iter().all(|&error| error < 1e-9),
+        "{:?}",
+        estimates
+    );
+}
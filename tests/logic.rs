@@ -0,0 +1,41 @@
+fn run(source: &str) -> String {
+    match ami::eval(source) {
+        Ok(value) => value.to_string(),
+        Err(error) => error.reason,
+    }
+}
+
+#[test]
+fn comparisons() {
+    assert_eq!(run("1 < 2"), "true");
+    assert_eq!(run("2 ≤ 2"), "true");
+    assert_eq!(run("3 > 4"), "false");
+    assert_eq!(run("1/2 = 0.5"), "true");
+    assert_eq!(run("1/3 ≠ 0.3"), "true");
+    assert_eq!(run("2 ≥ 3"), "false");
+}
+
+#[test]
+fn comparisons_chain() {
+    assert_eq!(run("1 < 2 < 3"), "true");
+    assert_eq!(run("1 < 3 < 2"), "false");
+    assert_eq!(run("3 > 2 > 2"), "false");
+    assert_eq!(run("0 ≤ 1/2 ≤ 1"), "true");
+}
+
+#[test]
+fn logic_short_circuits() {
+    assert_eq!(run("false and nothing"), "false");
+    assert_eq!(run("true or nothing"), "true");
+    assert_eq!(run("true and nothing"), "'nothing' is not defined");
+    assert_eq!(run("not (1 > 2) and 2 > 1"), "true");
+    assert_eq!(run("if 1 > 2 then nothing else 3"), "3");
+}
+
+#[test]
+fn booleans_are_literals() {
+    assert_eq!(run("true = false"), "false");
+    assert_eq!(run("true(1) = 1"), "expected a function, found true");
+    assert_eq!(run("truth = true; truth"), "true");
+    assert_eq!(run("x = false\nnot x"), "true");
+}
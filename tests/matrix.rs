@@ -0,0 +1,91 @@
+/// The numbers in the printed result of `source`, in order
+fn numbers(source: &str) -> Vec<f64> {
+    let value = ami::eval(source).unwrap().to_string();
+    value
+        .split(['[', ']', ',', ';', ' '])
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse().unwrap())
+        .collect()
+}
+
+fn assert_close(source: &str, expected: &[f64]) {
+    let actual = numbers(source);
+    assert_eq!(actual.len(), expected.len(), "{}", source);
+    for (a, e) in actual.iter().zip(expected) {
+        assert!(
+            (a - e).abs() <= 1e-9 * e.abs().max(1.0),
+            "{}: {:?}",
+            source,
+            actual
+        );
+    }
+}
+
+fn error(source: &str) -> String {
+    ami::eval(source).unwrap_err().reason
+}
+
+#[test]
+fn determinants() {
+    assert_close("det([1, 2; 3, 4])", &[-2.0]);
+    assert_close("det([2, 0, 0; 0, 3, 0; 0, 0, 4])", &[24.0]);
+    assert_close("det([0, 1; 1, 0])", &[-1.0]);
+    assert_close("det([1, 2; 2, 4])", &[0.0]);
+}
+
+#[test]
+fn inverses() {
+    assert_close("inv([1, 2; 3, 4])", &[-2.0, 1.0, 1.5, -0.5]);
+    assert_close("[1, 2; 3, 4]^-1", &[-2.0, 1.0, 1.5, -0.5]);
+    assert_close("inv([1e-13, 0; 0, 1e-13])", &[1e13, 0.0, 0.0, 1e13]);
+    assert_eq!(error("inv([1, 2; 2, 4])"), "the matrix is singular");
+}
+
+#[test]
+fn ranks() {
+    assert_close("rank([1e-13, 0; 0, 1e-13])", &[2.0]);
+    assert_close("rank([1, 2; 2, 4])", &[1.0]);
+    assert_close("rank([1, 2, 3; 4, 5, 6; 7, 8, 9])", &[2.0]);
+    assert_close("rank([0, 0; 0, 0])", &[0.0]);
+}
+
+#[test]
+fn linear_systems() {
+    assert_close("solve([2, 1; 1, 3], [3, 5])", &[0.8, 1.4]);
+    assert_close("solve([2, 1; 1, 3], [3, 1; 5, 2])", &[0.8, 0.2, 1.4, 0.6]);
+    assert_eq!(
+        error("solve([1, 2; 2, 4], [1, 2])"),
+        "the matrix is singular"
+    );
+}
+
+#[test]
+fn decompositions() {
+    assert_close(
+        "lu([1, 2; 3, 4])",
+        &[
+            1.0,
+            0.0,
+            1.0 / 3.0,
+            1.0,
+            3.0,
+            4.0,
+            0.0,
+            2.0 / 3.0,
+            0.0,
+            1.0,
+            1.0,
+            0.0,
+        ],
+    );
+    assert_close(
+        "qr([3, 0; 4, 5])",
+        &[-0.6, -0.8, -0.8, 0.6, -5.0, -4.0, 0.0, 3.0],
+    );
+    let half = 0.5_f64.sqrt();
+    assert_close("eig([2, 1; 1, 2])", &[1.0, 3.0, half, half, -half, half]);
+    assert_eq!(
+        error("eig([1, 2; 3, 4])"),
+        "eigendecomposition is only supported for symmetric matrices"
+    );
+}
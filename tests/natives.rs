@@ -0,0 +1,70 @@
+use std::{cell::Cell, ops::Range};
+
+use ami::{Engine, Value};
+
+fn error(source: &str) -> (String, Range<usize>) {
+    let error = ami::eval(source).unwrap_err();
+    (error.reason, error.range)
+}
+
+#[test]
+fn calls_with_the_wrong_number_of_arguments() {
+    assert_eq!(
+        error("sin(1, 2)"),
+        ("sin takes 1 argument, but was given 2".to_string(), 0..9)
+    );
+    assert_eq!(
+        error("max()"),
+        (
+            "max takes at least 1 argument, but was given 0".to_string(),
+            0..5
+        )
+    );
+    assert_eq!(
+        error("1 + clamp(1, 2)"),
+        (
+            "clamp takes 3 arguments, but was given 2".to_string(),
+            4..15
+        )
+    );
+
+    let mut engine = Engine::new();
+    engine.register("hypot", 1..=2, |_, args| Ok(args[0].clone()));
+    let error = engine.eval("hypot(1, 2, 3)").unwrap_err();
+    assert_eq!(
+        error.reason,
+        "hypot takes 1 to 2 arguments, but was given 3"
+    );
+}
+
+#[test]
+fn invalid_arguments_point_at_the_argument() {
+    assert_eq!(
+        error("det([1, 2])"),
+        ("expected a matrix, found [1, 2]".to_string(), 4..10)
+    );
+    assert_eq!(
+        error("inv(2)"),
+        ("expected a matrix, found 2".to_string(), 4..5)
+    );
+    assert_eq!(
+        error("gcd(4, i)"),
+        ("expected a number, found i".to_string(), 7..8)
+    );
+    assert_eq!(
+        error("map([1, 2], x ↦ x)"),
+        ("expected a function, found [1, 2]".to_string(), 4..10)
+    );
+}
+
+#[test]
+fn native_functions_keep_their_state() {
+    let mut engine = Engine::new();
+    let counter = Cell::new(0.0);
+    engine.register("next", 0..=0, move |_, _| {
+        counter.set(counter.get() + 1.0);
+        Ok(Value::Number(counter.get()))
+    });
+    assert_eq!(engine.eval("next()").unwrap().to_string(), "1");
+    assert_eq!(engine.eval("next() + next()").unwrap().to_string(), "5");
+}
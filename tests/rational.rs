@@ -0,0 +1,32 @@
+fn run(source: &str) -> String {
+    ami::eval(source).unwrap().to_string()
+}
+
+#[test]
+fn fractions_stay_exact() {
+    assert_eq!(run("1/3 + 1/6"), "1/2");
+    assert_eq!(run("1/3 * 3"), "1");
+    assert_eq!(run("3/4 - 3/4"), "0");
+    assert_eq!(run("-4/6"), "-2/3");
+    assert_eq!(run("abs(-3/4)"), "3/4");
+    assert_eq!(run("7/2 mod 1"), "1/2");
+    assert_eq!(run("floor(7/2)"), "3");
+}
+
+#[test]
+fn powers_of_fractions() {
+    assert_eq!(run("2^-3"), "1/8");
+    assert_eq!(run("(2/3)^-2"), "9/4");
+    assert_eq!(run("(1/2)^(-1)"), "2");
+    assert_eq!(run("(-2/3)^3"), "-8/27");
+    // Fractional exponents aren't exact any more
+    assert_eq!(run("(1/4)^(1/2)"), "0.5");
+}
+
+#[test]
+fn floats_take_over_when_mixed_in() {
+    assert_eq!(run("float(1/3)"), "0.3333333333333333");
+    assert_eq!(run("float(2)"), "2");
+    assert_eq!(run("1/3 + 0.5"), "0.8333333333333333");
+    assert_eq!(run("0.1 + 1/10"), "0.2");
+}
@@ -0,0 +1,63 @@
+fn solve(source: &str) -> String {
+    match ami::eval(source) {
+        Ok(value) => value.to_string(),
+        Err(error) => error.reason,
+    }
+}
+
+#[test]
+fn closed_forms() {
+    assert_eq!(solve("solve(x² = 4, x)"), "[-2, 2]");
+    assert_eq!(solve("solve(2x + 1 = 0, x)"), "[-0.5]");
+    assert_eq!(solve("solve(x = x, x)"), "every value of x is a solution");
+}
+
+#[test]
+fn polynomials_have_complex_roots_at_every_degree() {
+    assert_eq!(solve("solve(x² + 1 = 0, x)"), "[-i, i]");
+    assert_eq!(
+        solve("solve(x³ - 1 = 0, x)"),
+        "[1, -0.5 - 0.8660254037844386i, -0.5 + 0.8660254037844386i]"
+    );
+    assert_eq!(solve("solve(x⁴ - 1 = 0, x)"), "[-1, 1, -i, i]");
+    assert_eq!(solve("solve(x⁵ - x = 0, x)"), "[-1, 0, 1, -i, i]");
+    assert_eq!(solve("solve(x⁴ - 10x² + 9 = 0, x)"), "[-3, -1, 1, 3]");
+}
+
+#[test]
+fn repeated_roots_are_found_once() {
+    assert_eq!(solve("solve((x - 1)⁴ = 0, x)"), "[1]");
+    assert_eq!(solve("solve((x - 1)² * (x - 2)³ = 0, x)"), "[1, 2]");
+    assert_eq!(solve("solve(x⁴ = 0, x)"), "[0]");
+}
+
+#[test]
+fn numerical_roots() {
+    assert_eq!(solve("solve(ln(x) = 1, x)"), "[2.718281828459045]");
+    assert_eq!(solve("solve(cos, 1)"), "[1.5707963267948966]");
+}
+
+#[test]
+fn numerical_roots_are_looked_for_between_minus_100_and_100() {
+    let roots = solve("solve(sin(x) = 0, x)");
+    assert!(roots.starts_with("[-97.3893722612836, "), "{}", roots);
+    assert!(roots.ends_with(", 97.3893722612836]"), "{}", roots);
+    assert_eq!(roots.matches(',').count() + 1, 63);
+    assert_eq!(
+        solve("solve(e^x = 0, x)"),
+        "couldn't find any real roots between -100 and 100"
+    );
+}
+
+#[test]
+fn equations_that_are_zero_on_an_interval() {
+    assert_eq!(solve("solve(x/x = 1, x)"), "every value of x is a solution");
+    assert_eq!(
+        solve("solve(floor(x) = 0, x)"),
+        "every value of x between 0 and 1 is a solution"
+    );
+    assert_eq!(
+        solve("solve(0 = min(x, 0), x)"),
+        "every value of x from 0 up is a solution"
+    );
+}
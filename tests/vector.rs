@@ -0,0 +1,68 @@
+fn eval(source: &str) -> String {
+    match ami::eval(source) {
+        Ok(value) => value.to_string(),
+        Err(error) => error.reason,
+    }
+}
+
+#[test]
+fn dot_products() {
+    assert_eq!(eval("[1, 2, 3] ∙ [4, 5, 6]"), "32");
+    assert_eq!(eval("[1, 0] ∙ [0, 1]"), "0");
+    assert_eq!(
+        eval("[1, 2] ∙ [1, 2, 3]"),
+        "vectors have different lengths, 2 and 3"
+    );
+}
+
+#[test]
+fn cross_products() {
+    assert_eq!(eval("[1, 0, 0] × [0, 1, 0]"), "[0, 0, 1]");
+    assert_eq!(eval("[1, 2, 3] × [4, 5, 6]"), "[-3, 6, -3]");
+    assert_eq!(
+        eval("[1, 2] × [3, 4]"),
+        "cross products need 3-D vectors, not 2-D"
+    );
+}
+
+#[test]
+fn norms() {
+    assert_eq!(eval("|[3, 4]|"), "5");
+    assert_eq!(eval("|[0, 0, 0]|"), "0");
+}
+
+#[test]
+fn element_wise_arithmetic() {
+    assert_eq!(eval("[1, 2] + [3, 4]"), "[4, 6]");
+    assert_eq!(eval("[1, 2] - [3, 5]"), "[-2, -3]");
+    assert_eq!(eval("⟨1, 2⟩ + ⟨3, 4⟩"), "[4, 6]");
+    assert_eq!(
+        eval("[1, 2] + [1, 2, 3]"),
+        "vectors have different lengths, 2 and 3"
+    );
+    assert_eq!(
+        eval("[1, 2, 3] - [1, 2]"),
+        "vectors have different lengths, 3 and 2"
+    );
+}
+
+#[test]
+fn scalar_multiplication() {
+    assert_eq!(eval("3 * [1, 2]"), "[3, 6]");
+    assert_eq!(eval("[1, 2] * 3"), "[3, 6]");
+    assert_eq!(eval("[1, 2] / 2"), "[1/2, 1]");
+}
+
+#[test]
+fn indexing() {
+    assert_eq!(eval("v = [10, 20, 30]\nv[1]"), "10");
+    assert_eq!(eval("v = [10, 20, 30]\nv[3]"), "30");
+    assert_eq!(
+        eval("v = [10, 20, 30]\nv[4]"),
+        "expected an integer from 1 to 3, found 4"
+    );
+    assert_eq!(
+        eval("v = [10, 20, 30]\nv[0]"),
+        "expected an integer from 1 to 3, found 0"
+    );
+}